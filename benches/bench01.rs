@@ -10,6 +10,12 @@ pub fn bench(c: &mut Criterion) {
 
 	group.bench_function("part1", |b| b.iter(|| part1(input)));
 	group.bench_function("part2", |b| b.iter(|| part2(input)));
+	// both parts parsing the input separately, against a single parse shared between them
+	group.bench_function("both_separate", |b| b.iter(|| (part1(input).unwrap(), part2(input).unwrap())));
+	group.bench_function("both_shared", |b| b.iter(|| {
+		let parsed = parse_input(input).unwrap();
+		(part1_parsed(&parsed), part2_parsed(&parsed))
+	}));
 }
 
 criterion_group!(benches, bench);
@@ -9,6 +9,7 @@ pub fn bench(c: &mut Criterion) {
 	let mut group = c.benchmark_group("day09");
 
 	group.bench_function("part1", |b| b.iter(|| part1(input)));
+	group.bench_function("part1_btree", |b| b.iter(|| part1_btree(input)));
 	group.bench_function("part2", |b| b.iter(|| part2(input)));
 }
 
@@ -9,6 +9,9 @@ pub fn bench(c: &mut Criterion) {
 	let mut group = c.benchmark_group("day16");
 
 	group.bench_function("part1", |b| b.iter(|| part1(input)));
+	// search only, with the map parsed up front
+	let map = parse_input(input).unwrap();
+	group.bench_function("part1_grid", |b| b.iter(|| part1_grid(&map)));
 	group.bench_function("part2", |b| b.iter(|| part2(input)));
 }
 
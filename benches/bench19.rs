@@ -9,6 +9,7 @@ pub fn bench(c: &mut Criterion) {
 	let mut group = c.benchmark_group("day19");
 
 	group.bench_function("part1", |b| b.iter(|| part1(input)));
+	group.bench_function("part1_split", |b| b.iter(|| part1_split(input)));
 	group.bench_function("part2", |b| b.iter(|| part2(input)));
 }
 
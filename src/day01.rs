@@ -3,7 +3,7 @@ use ahash::AHashMap;
 use anyhow::*;
 use nom::character::complete::{digit1, line_ending, space1};
 use nom::combinator::{all_consuming, map_res};
-use nom::{IResult};
+use nom::{Finish, IResult};
 use nom::multi::separated_list1;
 use nom::sequence::separated_pair;
 
@@ -15,32 +15,49 @@ fn parse_line(input: &str) -> IResult<&str, (u64, u64)> {
 	separated_pair(parse_u64, space1, parse_u64)(input)
 }
 
-fn parse(input: &str) -> Vec<(u64, u64)> {
-	all_consuming(separated_list1(
+pub fn parse_input(input: &str) -> Result<Vec<(u64, u64)>> {
+	let (_, result) = all_consuming(separated_list1(
 		line_ending,
 		parse_line
-	))(input).unwrap().1
+	))(input).finish()
+		.map_err(|err| anyhow!("could not parse input at {:?}: {:?}", err.input.lines().next().unwrap_or_default(), err.code))?;
+	Ok(result)
 }
 
+pub struct Day01;
+
 pub fn part1(input: &str) -> Result<u64> {
-	let parsed = parse(input);
-	let (mut left, mut right): (Vec<_>, Vec<_>) = parsed.into_iter().unzip();
+	Ok(part1_parsed(&parse_input(input)?))
+}
+
+pub fn part2(input: &str) -> Result<u64> {
+	Ok(part2_parsed(&parse_input(input)?))
+}
+
+// both answers from a single parse of the input
+pub fn solve_both(input: &str) -> Result<(String, String)> {
+	let parsed = parse_input(input)?;
+
+	Ok((part1_parsed(&parsed).to_string(), part2_parsed(&parsed).to_string()))
+}
+
+pub fn part1_parsed(parsed: &[(u64, u64)]) -> u64 {
+	let (mut left, mut right): (Vec<_>, Vec<_>) = parsed.iter().copied().unzip();
 
 	left.sort();
 	right.sort();
 
-	Ok(left.into_iter().zip(right).map(|(a, b)| max(a, b) - min(a, b)).sum())
+	left.into_iter().zip(right).map(|(a, b)| max(a, b) - min(a, b)).sum()
 }
 
-pub fn part2(input: &str) -> Result<u64> {
-	let parsed = parse(input);
-	let (left, right): (Vec<_>, Vec<_>) = parsed.into_iter().unzip();
+pub fn part2_parsed(parsed: &[(u64, u64)]) -> u64 {
+	let (left, right): (Vec<_>, Vec<_>) = parsed.iter().copied().unzip();
 
 	let mut count_map = AHashMap::new();
 	right.into_iter().for_each(|val| {
 		count_map.entry(val).and_modify(|cnt| *cnt += 1).or_insert(1u64);
 	});
-	Ok(left.into_iter().map(|val| val * count_map.get(&val).unwrap_or(&0)).sum())
+	left.into_iter().map(|val| val * count_map.get(&val).unwrap_or(&0)).sum()
 }
 
 #[cfg(test)]
@@ -68,4 +85,17 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[test]
+	fn test_solve_both() -> Result<()> {
+		assert_eq!((part1(TEST)?.to_string(), part2(TEST)?.to_string()), solve_both(TEST)?);
+		assert!(solve_both("3   4\n4").is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_truncated_input() {
+		assert!(part1("3   4\n4").is_err());
+		assert!(part2("3   4\n4").is_err());
+	}
 }
@@ -2,8 +2,9 @@ use anyhow::*;
 use nom::character::complete::{digit1, line_ending, space1};
 use nom::combinator::{all_consuming, map_res};
 use nom::multi::separated_list1;
-use nom::IResult;
+use nom::{Finish, IResult};
 use std::cmp::{max, min, Ordering, PartialEq};
+use std::io::BufRead;
 use std::ops::ControlFlow;
 
 fn parse_u64(input: &str) -> IResult<&str, u64> {
@@ -57,47 +58,53 @@ impl PreviousLevel {
 	}
 }
 
-pub fn part1(input: &str) -> Result<u64> {
-	let reports = parse(input);
-
-	let safe = reports.into_iter().fold(0, |cnt, report| {
-		let x = report
-			.into_iter()
-			.try_fold(None, |prev_level: Option<PreviousLevel>, level| {
-				if let Some(prev_level) = prev_level {
-					let pass_val = PreviousLevel::continue_from(prev_level.value, level);
-					let value_diff = max(prev_level.value, level) - min(prev_level.value, level);
-					if prev_level.direction != LevelDirection::Unknown {
-						if value_diff > 3 || pass_val.direction != prev_level.direction {
-							ControlFlow::Break(())
-						} else {
-							ControlFlow::Continue(Some(pass_val))
-						}
+fn is_strictly_safe(report: &[u64]) -> bool {
+	let x = report
+		.iter()
+		.try_fold(None, |prev_level: Option<PreviousLevel>, &level| {
+			if let Some(prev_level) = prev_level {
+				let pass_val = PreviousLevel::continue_from(prev_level.value, level);
+				let value_diff = max(prev_level.value, level) - min(prev_level.value, level);
+				if prev_level.direction != LevelDirection::Unknown {
+					if value_diff > 3 || pass_val.direction != prev_level.direction {
+						ControlFlow::Break(())
 					} else {
-						// don't know direction yet, only check value difference
-						if value_diff > 3 || pass_val.direction == LevelDirection::Unchanged {
-							ControlFlow::Break(())
-						} else {
-							ControlFlow::Continue(Some(pass_val))
-						}
+						ControlFlow::Continue(Some(pass_val))
 					}
 				} else {
-					// prev_level empty means this is the first iteration
-					// always considered safe at this point, just continue
-					ControlFlow::Continue(Some(PreviousLevel::start_at(level)))
+					// don't know direction yet, only check value difference
+					if value_diff > 3 || pass_val.direction == LevelDirection::Unchanged {
+						ControlFlow::Break(())
+					} else {
+						ControlFlow::Continue(Some(pass_val))
+					}
 				}
-			});
-		match x {
-			ControlFlow::Continue(_) => cnt + 1,
-			ControlFlow::Break(_) => cnt
-		}
-	});
+			} else {
+				// prev_level empty means this is the first iteration
+				// always considered safe at this point, just continue
+				ControlFlow::Continue(Some(PreviousLevel::start_at(level)))
+			}
+		});
+	x.is_continue()
+}
 
-	Ok(safe)
+pub struct Day02;
+
+pub fn part1(input: &str) -> Result<u64> {
+	Ok(part1_safe_indices(input)?.len() as u64)
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum ReactorStatus {
+// line indices of every report that is safe without the Problem Dampener
+pub fn part1_safe_indices(input: &str) -> Result<Vec<usize>> {
+	let reports = parse(input);
+
+	Ok(reports.into_iter().enumerate()
+		.filter_map(|(ix, report)| is_strictly_safe(&report).then_some(ix))
+		.collect())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReactorStatus {
 	Safe,
 	Tolerable,
 	Unsafe,
@@ -110,6 +117,12 @@ impl ReactorStatus {
 }
 
 fn process_report(report: &[u64]) -> ReactorStatus {
+	let (status, _) = analyze_report(report);
+	status
+}
+
+// also returns the index of the level the Problem Dampener skipped, if it was needed
+pub fn analyze_report(report: &[u64]) -> (ReactorStatus, Option<usize>) {
 	fn calc_level_diff(prev_level: u64, next_level: u64) -> (LevelDirection, u64) {
 		match next_level.cmp(&prev_level) {
 			Ordering::Less => (LevelDirection::Decreasing, prev_level - next_level),
@@ -118,20 +131,20 @@ fn process_report(report: &[u64]) -> ReactorStatus {
 		}
 	}
 
-	fn process_report_rec(prev_level: Option<u64>, dir: LevelDirection, reports_to_process: &[u64], status: ReactorStatus) -> ReactorStatus {
+	fn process_report_rec(prev_level: Option<u64>, dir: LevelDirection, reports_to_process: &[u64], ix: usize, status: ReactorStatus) -> (ReactorStatus, Option<usize>) {
 		if status == ReactorStatus::Unsafe {
 			// nothing can ever bring a reactor back from Unsafe
-			return ReactorStatus::Unsafe;
+			return (ReactorStatus::Unsafe, None);
 		}
 		if dir == LevelDirection::Unchanged {
 			// an unchanging level means an Unsafe reactor
-			return ReactorStatus::Unsafe;
+			return (ReactorStatus::Unsafe, None);
 		}
 
 		let (&level, remaining_reports) = match reports_to_process.split_first() {
 			Some(v) => v,
 			// split_first() returns None when reports_to_process is empty, return current status
-			None => return status,
+			None => return (status, None),
 		};
 
 		// process assuming the current level will be executed
@@ -142,9 +155,9 @@ fn process_report(report: &[u64]) -> ReactorStatus {
 				let (new_dir, value_delta) = calc_level_diff(prev_level, level);
 				if dir != new_dir || value_delta > 3 {
 					// if we include this value, then...
-					ReactorStatus::Unsafe
+					(ReactorStatus::Unsafe, None)
 				} else {
-					process_report_rec(Some(level), new_dir, remaining_reports, status)
+					process_report_rec(Some(level), new_dir, remaining_reports, ix + 1, status)
 				}
 			} else {
 				// no level direction yet, we are the second level
@@ -152,47 +165,80 @@ fn process_report(report: &[u64]) -> ReactorStatus {
 				let (starting_dir, value_delta) = calc_level_diff(prev_level, level);
 				if value_delta == 0 || value_delta > 3 {
 					// if we include this value, then...
-					ReactorStatus::Unsafe
+					(ReactorStatus::Unsafe, None)
 				} else {
-					process_report_rec(Some(level), starting_dir, remaining_reports, status)
+					process_report_rec(Some(level), starting_dir, remaining_reports, ix + 1, status)
 				}
 			}
 		} else {
 			// no previous level means this is the first level
-			process_report_rec(Some(level), dir, remaining_reports, status)
+			process_report_rec(Some(level), dir, remaining_reports, ix + 1, status)
 		};
 
 		// if we haven't utilized the Problem Dampener yet and executing this step led to the reactor becoming unsafe...
-		if status == ReactorStatus::Safe && recurse == ReactorStatus::Unsafe {
+		if status == ReactorStatus::Safe && recurse.0 == ReactorStatus::Unsafe {
 			// then utilize the Problem Dampener to skip this level reading
 			// nothing else we can do if skipping this step doesn't make the reactor safer
-			process_report_rec(prev_level, dir, remaining_reports, ReactorStatus::Tolerable)
+			let (skipped_status, _) = process_report_rec(prev_level, dir, remaining_reports, ix + 1, ReactorStatus::Tolerable);
+			(skipped_status, skipped_status.is_safe().then_some(ix))
 		} else {
 			recurse
 		}
 	}
 
-	process_report_rec(None, LevelDirection::Unknown, report, ReactorStatus::Safe)
+	process_report_rec(None, LevelDirection::Unknown, report, 0, ReactorStatus::Safe)
 }
 
 pub fn part2(input: &str) -> Result<u64> {
+	Ok(part2_safe_indices(input)?.len() as u64)
+}
+
+// line indices of every report that is safe, with the Problem Dampener's help if needed
+pub fn part2_safe_indices(input: &str) -> Result<Vec<usize>> {
 	let reports = parse(input);
 
-	let safe = reports.into_iter().fold(0, |cnt, report| {
-		let status = process_report(&report);
-		if status.is_safe() {
-			cnt + 1
-		} else {
-			cnt
+	Ok(reports.into_iter().enumerate()
+		.filter_map(|(ix, report)| process_report(&report).is_safe().then_some(ix))
+		.collect())
+}
+
+fn is_safe_with_removals(report: &[u64], max_removals: usize) -> bool {
+	match max_removals {
+		0 => is_strictly_safe(report),
+		1 => process_report(report).is_safe(),
+		_ => {
+			// the Problem Dampener only ever handles a single level, beyond that try each removal in turn
+			is_strictly_safe(report) || (0..report.len()).any(|skip_ix| {
+				let mut reduced_report = report.to_vec();
+				reduced_report.remove(skip_ix);
+				is_safe_with_removals(&reduced_report, max_removals - 1)
+			})
+		}
+	}
+}
+
+pub fn count_safe_reader<R: BufRead>(reader: R, max_removals: usize) -> Result<u64> {
+	// reports are independent of each other, so there is no need to hold the whole input in memory
+	reader.lines().try_fold(0, |cnt, line| {
+		let line = line?;
+		if line.trim().is_empty() {
+			return Ok(cnt);
 		}
-	});
 
-	Ok(safe)
+		let (_, report) = all_consuming(parse_line)(line.trim()).finish()
+			.map_err(|err| anyhow!("could not parse report {:?}: {:?}", line, err.code))?;
+		if is_safe_with_removals(&report, max_removals) {
+			Ok(cnt + 1)
+		} else {
+			Ok(cnt)
+		}
+	})
 }
 
 #[cfg(test)]
 mod tests {
 	use crate::day02::*;
+	use std::io::Cursor;
 
 	const TEST: &str = "7 6 4 2 1
 1 2 7 8 9
@@ -212,4 +258,34 @@ mod tests {
 		assert_eq!(4, part2(TEST)?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_safe_indices() -> Result<()> {
+		assert_eq!(vec![0, 5], part1_safe_indices(TEST)?);
+		assert_eq!(vec![0, 3, 4, 5], part2_safe_indices(TEST)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_count_safe_reader() -> Result<()> {
+		assert_eq!(2, count_safe_reader(Cursor::new(TEST), 0)?);
+		assert_eq!(4, count_safe_reader(Cursor::new(TEST), 1)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_analyze_report() {
+		let report = [8, 6, 4, 4, 1];
+		let (status, removed_ix) = analyze_report(&report);
+		assert_eq!(ReactorStatus::Tolerable, status);
+		let removed_ix = removed_ix.unwrap();
+		assert!(removed_ix == 2 || removed_ix == 3);
+
+		let mut dampened = report.to_vec();
+		dampened.remove(removed_ix);
+		assert!(is_strictly_safe(&dampened));
+
+		assert_eq!((ReactorStatus::Safe, None), analyze_report(&[7, 6, 4, 2, 1]));
+		assert_eq!((ReactorStatus::Unsafe, None), analyze_report(&[1, 2, 7, 8, 9]));
+	}
 }
@@ -1,11 +1,11 @@
 use anyhow::*;
 use nom::bytes::complete::{tag, take, take_while_m_n};
 use nom::character::complete::char;
-use nom::combinator::{map, map_res, value};
+use nom::combinator::{map, map_res, value, verify};
 use nom::{AsChar, IResult};
 use nom::branch::alt;
-use nom::multi::{many1, many_till};
-use nom::sequence::{delimited, separated_pair};
+use nom::multi::{many0, many_till, separated_list1};
+use nom::sequence::delimited;
 
 fn digit1to3(input: &str) -> IResult<&str, &str> {
 	take_while_m_n(1, 3, AsChar::is_dec_digit)(input)
@@ -15,70 +15,68 @@ fn parse1to3_u64(input: &str) -> IResult<&str, u64> {
 	map_res(digit1to3, |num: &str| num.parse())(input)
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-struct MultiplyInstruction {
-	a: u64,
-	b: u64
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultiplyInstruction {
+	factors: Vec<u64>
 }
 impl MultiplyInstruction {
 	pub fn from((a, b): (u64, u64)) -> Self {
-		Self { a, b }
+		Self { factors: vec![a, b] }
+	}
+	pub fn from_factors(factors: Vec<u64>) -> Self {
+		Self { factors }
 	}
 	pub fn result(&self) -> u64 {
-		self.a * self.b
+		self.factors.iter().product()
 	}
 }
 
-fn parse_mul_instruction(input: &str) -> IResult<&str, MultiplyInstruction> {
-	map(delimited(
+// mul with anywhere from two up to max_factors comma separated numbers
+fn parse_mul_n(max_factors: usize) -> impl FnMut(&str) -> IResult<&str, MultiplyInstruction> {
+	move |input| map(delimited(
 		tag("mul("),
-		separated_pair(
-			parse1to3_u64,
-			char(','),
-			parse1to3_u64,
+		verify(
+			separated_list1(char(','), parse1to3_u64),
+			|factors: &Vec<u64>| (2..=max_factors).contains(&factors.len())
 		),
 		tag(")")
-	), |val| MultiplyInstruction::from(val))(input)
+	), MultiplyInstruction::from_factors)(input)
 }
 
-fn take_till_mul_instruction(input: &str) -> IResult<&str, MultiplyInstruction> {
-	map(many_till(
+fn take_till_mul_instruction(max_factors: usize) -> impl FnMut(&str) -> IResult<&str, MultiplyInstruction> {
+	move |input| map(many_till(
 		take(1usize),
-		parse_mul_instruction
+		parse_mul_n(max_factors)
 	), |(_, mul)| mul)(input)
 }
 
-fn many1_mul_instruction(input: &str) -> IResult<&str, Vec<MultiplyInstruction>> {
-	many1(take_till_mul_instruction)(input)
-}
-
-fn parse(input: &str) -> Vec<MultiplyInstruction> {
-	let (_, parsed) = many1_mul_instruction(input).unwrap();
+// corrupted memory without any instructions is still valid, it just doesn't do anything
+fn parse(input: &str, max_factors: usize) -> Vec<MultiplyInstruction> {
+	let (_, parsed) = many0(take_till_mul_instruction(max_factors))(input).unwrap();
 	parsed
 }
 
+pub struct Day03;
+
 pub fn part1(input: &str) -> Result<u64> {
-	let valid_instructions = parse(input);
+	part1_with_max_factors(input, 2)
+}
+
+// for corrupted memory variants where mul can take more than two numbers
+pub fn part1_with_max_factors(input: &str, max_factors: usize) -> Result<u64> {
+	let valid_instructions = parse(input, max_factors);
 	Ok(valid_instructions.into_iter().fold(0, |sum, inst| sum + inst.result()))
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Instruction {
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Instruction {
 	Multiply(MultiplyInstruction),
 	Enable,
 	Disable
 }
 
 fn parse_mul_instruction_part2(input: &str) -> IResult<&str, Instruction> {
-	map(delimited(
-		tag("mul("),
-		separated_pair(
-			parse1to3_u64,
-			char(','),
-			parse1to3_u64,
-		),
-		tag(")")
-	), |val| Instruction::Multiply(MultiplyInstruction::from(val)))(input)
+	map(parse_mul_n(2), Instruction::Multiply)(input)
 }
 
 fn parse_enable_instruction(input: &str) -> IResult<&str, Instruction> {
@@ -89,19 +87,36 @@ fn parse_disable_instruction(input: &str) -> IResult<&str, Instruction> {
 	value(Instruction::Disable, tag("don't()"))(input)
 }
 
-fn take_till_instruction(input: &str) -> IResult<&str, Instruction> {
+// also returns the number of bytes skipped before the instruction
+fn take_till_instruction_with_skipped(input: &str) -> IResult<&str, (usize, Instruction)> {
 	map(many_till(
 		take(1usize),
 		alt((parse_enable_instruction, parse_disable_instruction, parse_mul_instruction_part2))
-	), |(_, inst)| inst)(input)
+	), |(skipped, inst): (Vec<&str>, _)| (skipped.into_iter().map(str::len).sum(), inst))(input)
+}
+
+fn take_till_instruction(input: &str) -> IResult<&str, Instruction> {
+	map(take_till_instruction_with_skipped, |(_, inst)| inst)(input)
 }
 
-fn many1_instruction(input: &str) -> IResult<&str, Vec<Instruction>> {
-	many1(take_till_instruction)(input)
+// byte offset of the start of every instruction, along with the instruction
+pub fn scan_instructions(input: &str) -> Vec<(usize, Instruction)> {
+	let mut instructions = Vec::new();
+	let mut remaining = input;
+	while let Result::Ok((rest, (skipped, inst))) = take_till_instruction_with_skipped(remaining) {
+		let start = (input.len() - remaining.len()) + skipped;
+		instructions.push((start, inst));
+		remaining = rest;
+	}
+	instructions
+}
+
+fn many0_instruction(input: &str) -> IResult<&str, Vec<Instruction>> {
+	many0(take_till_instruction)(input)
 }
 
 fn parse_part2(input: &str) -> Vec<Instruction> {
-	let (_, parsed) = many1_instruction(input).unwrap();
+	let (_, parsed) = many0_instruction(input).unwrap();
 	parsed
 }
 
@@ -143,4 +158,35 @@ mod tests {
 		assert_eq!(48, part2(TEST2)?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_scan_instructions() {
+		let instructions = scan_instructions(TEST2);
+		assert_eq!(6, instructions.len());
+		assert_eq!((1, Instruction::Multiply(MultiplyInstruction::from((2, 4)))), instructions[0]);
+		assert_eq!((20, Instruction::Disable), instructions[1]);
+		assert!(instructions.iter().all(|&(start, ref inst)| match inst {
+			Instruction::Multiply(_) => TEST2[start..].starts_with("mul("),
+			Instruction::Enable => TEST2[start..].starts_with("do()"),
+			Instruction::Disable => TEST2[start..].starts_with("don't()"),
+		}));
+	}
+
+	#[test]
+	fn test_mul_n() -> Result<()> {
+		assert_eq!(0, part1("mul(2,3,4)")?);
+		assert_eq!(24, part1_with_max_factors("mul(2,3,4)", 3)?);
+		assert_eq!(161, part1_with_max_factors(TEST, 3)?);
+		// too many factors, or just one, is still corrupted
+		assert_eq!(6, part1_with_max_factors("mul(2,3,4,5)mul(7)mul(2,3)", 3)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_no_instructions() -> Result<()> {
+		assert_eq!(0, part1("no instructions here")?);
+		assert_eq!(0, part2("no instructions here")?);
+		assert_eq!(0, part1("")?);
+		Ok(())
+	}
 }
@@ -1,43 +1,13 @@
 use anyhow::*;
 use grid::Grid;
-use nom::branch::alt;
-use nom::character::complete::{char, line_ending};
-use nom::combinator::{all_consuming, opt, value};
-use nom::{Finish, IResult};
-use nom::multi::many1;
-use nom::sequence::terminated;
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Letter {
-	X,
-	M,
-	A,
-	S,
-}
-
-fn parse_letter(input: &str) -> IResult<&str, Letter> {
-	alt((
-		value(Letter::X, char('X')),
-		value(Letter::M, char('M')),
-		value(Letter::A, char('A')),
-		value(Letter::S, char('S')),
-	))(input)
-}
-
-fn parse_row(input: &str) -> IResult<&str, Vec<Letter>> {
-	terminated(
-		many1(parse_letter),
-		opt(line_ending)
-	)(input)
-}
+use crate::grid_parse::parse_grid;
 
-fn parse(input: &str) -> Result<Vec<Vec<Letter>>> {
-	let (_, result) = all_consuming(many1(parse_row))(input).finish().unwrap();
-	Ok(result)
+fn parse(input: &str) -> Result<Grid<char>> {
+	parse_grid(input, |chr| (!chr.is_whitespace()).then_some(chr))
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Direction {
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
 	North,
 	Northeast,
 	East,
@@ -50,13 +20,13 @@ enum Direction {
 impl Direction {
 	pub fn get_offset(&self) -> (isize, isize) {
 		match self {
-			Direction::North => (0, -1),
-			Direction::Northeast => (1, -1),
-			Direction::East => (1, 0),
+			Direction::North => (-1, 0),
+			Direction::Northeast => (-1, 1),
+			Direction::East => (0, 1),
 			Direction::Southeast => (1, 1),
-			Direction::South => (0, 1),
-			Direction::Southwest => (-1, 1),
-			Direction::West => (-1, 0),
+			Direction::South => (1, 0),
+			Direction::Southwest => (1, -1),
+			Direction::West => (0, -1),
 			Direction::Northwest => (-1, -1),
 		}
 	}
@@ -74,31 +44,42 @@ impl Direction {
 	}
 }
 
+pub struct Day04;
+
 pub fn part1(input: &str) -> Result<u64> {
-	let wordsearch: Grid<_> = parse(input)?.into();
-
-	const TARGET: &[Letter; 4] = &[Letter::X, Letter::M, Letter::A, Letter::S];
-	const TARGET_TUPLE: (&Letter, &[Letter]) = TARGET.split_first().unwrap();
-	const TARGET_START: &Letter = TARGET_TUPLE.0;
-	const TARGET_REST: &[Letter] = TARGET_TUPLE.1;
-
-	let result = wordsearch.indexed_iter().map(|((x, y), letter)| {
-		let (x, y) = (isize::try_from(x).unwrap(), isize::try_from(y).unwrap());
-		if letter == TARGET_START {
-			Direction::iter().map(|dir| {
-				let (x_offset, y_offset) = dir.get_offset();
-				let found_target = TARGET_REST.into_iter().enumerate().all(|(ix, target_letter)| {
-					let ix = isize::try_from(ix).unwrap();
-					let (next_x, next_y) = (x + (x_offset * (ix + 1)), y + (y_offset * (ix + 1)));
-					wordsearch.get(next_x, next_y).is_some_and(|grid_letter| grid_letter == target_letter)
-				});
+	count_word(input, "XMAS")
+}
+
+pub fn count_word(input: &str, word: &str) -> Result<u64> {
+	Ok(find_matches(input, word)?.len() as u64)
+}
 
-				if found_target { 1 } else { 0 }
-			}).sum()
+// (row, col) of the first letter of every match, along with the direction the rest of the word runs in
+pub fn find_matches(input: &str, word: &str) -> Result<Vec<((isize, isize), Direction)>> {
+	let wordsearch = parse(input)?;
+
+	let target = word.chars().collect::<Vec<_>>();
+	let Some((target_start, target_rest)) = target.split_first() else {
+		bail!("can't search for an empty word");
+	};
+
+	let result = wordsearch.indexed_iter().flat_map(|((row, col), letter)| {
+		let (row, col) = (isize::try_from(row).unwrap(), isize::try_from(col).unwrap());
+		let found_directions = if letter == target_start {
+			Direction::iter().filter(|dir| {
+				let (row_offset, col_offset) = dir.get_offset();
+				target_rest.iter().enumerate().all(|(ix, target_letter)| {
+					let ix = isize::try_from(ix).unwrap();
+					let (next_row, next_col) = (row + (row_offset * (ix + 1)), col + (col_offset * (ix + 1)));
+					wordsearch.get(next_row, next_col).is_some_and(|grid_letter| grid_letter == target_letter)
+				})
+			}).collect()
 		} else {
-			0
-		}
-	}).sum();
+			Vec::new()
+		};
+
+		found_directions.into_iter().map(move |dir| ((row, col), dir))
+	}).collect();
 
 	Ok(result)
 }
@@ -127,41 +108,33 @@ impl Direction {
 }
 
 pub fn part2(input: &str) -> Result<u64> {
-	let wordsearch: Grid<_> = parse(input)?.into();
+	Ok(find_x_mas(input)?.len() as u64)
+}
+
+// (row, col) of the A at the center of every X-MAS
+pub fn find_x_mas(input: &str) -> Result<Vec<(isize, isize)>> {
+	let wordsearch = parse(input)?;
 
-	const TARGET_START: &Letter = &Letter::A;
+	const TARGET_START: &char = &'A';
 	const DIR_SEARCH: [Direction; 4] = Direction::diagonals();
-	const TARGET_DIAGONAL: &Letter = &Letter::M;
-	const TARGET_OPPOSITE: &Letter = &Letter::S;
-
-	let result = wordsearch.indexed_iter().map(|((x, y), letter)| {
-		let (x, y) = (isize::try_from(x).unwrap(), isize::try_from(y).unwrap());
-		if letter == TARGET_START {
-			let found_patterns: u8 = DIR_SEARCH.into_iter().map(|dir| {
-				let (x_init_offset, y_init_offset) = dir.get_offset();
-				let (next_x, next_y) = (x + x_init_offset, y + y_init_offset);
-				if wordsearch.get(next_x, next_y).is_some_and(|diag_letter| diag_letter == TARGET_DIAGONAL) {
-					let (x_opp_offset, y_opp_offset) = dir.opposite().get_offset();
-					let (opp_x, opp_y) = (x + x_opp_offset, y + y_opp_offset);
-					if wordsearch.get(opp_x, opp_y).is_some_and(|opp_letter| opp_letter == TARGET_OPPOSITE) {
-						1
-					} else {
-						0
-					}
-				} else {
-					0
-				}
-			}).sum();
-
-			if found_patterns == 2 {
-				1
-			} else {
-				0
-			}
-		} else {
-			0
+	const TARGET_DIAGONAL: &char = &'M';
+	const TARGET_OPPOSITE: &char = &'S';
+
+	let result = wordsearch.indexed_iter().filter_map(|((row, col), letter)| {
+		let (row, col) = (isize::try_from(row).unwrap(), isize::try_from(col).unwrap());
+		if letter != TARGET_START {
+			return None;
 		}
-	}).sum();
+
+		let found_patterns = DIR_SEARCH.into_iter().filter(|dir| {
+			let (row_offset, col_offset) = dir.get_offset();
+			let (opp_row_offset, opp_col_offset) = dir.opposite().get_offset();
+			wordsearch.get(row + row_offset, col + col_offset).is_some_and(|diag_letter| diag_letter == TARGET_DIAGONAL)
+				&& wordsearch.get(row + opp_row_offset, col + opp_col_offset).is_some_and(|opp_letter| opp_letter == TARGET_OPPOSITE)
+		}).count();
+
+		(found_patterns == 2).then_some((row, col))
+	}).collect();
 
 	Ok(result)
 }
@@ -187,9 +160,38 @@ MXMXAXMASX";
 		Ok(())
 	}
 
+	#[test]
+	fn test_count_word() -> Result<()> {
+		// every XMAS read backwards is a SAMX
+		assert_eq!(18, count_word(TEST, "SAMX")?);
+		assert_eq!(count_word(TEST, "XMAS")?, count_word(TEST, "SAMX")?);
+		assert_eq!(5, count_word(TEST, "XMASX")?);
+		assert!(count_word(TEST, "").is_err());
+		Ok(())
+	}
+
 	#[test]
 	fn test_part_two() -> Result<()> {
 		assert_eq!(9, part2(TEST)?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_find_matches() -> Result<()> {
+		let matches = find_matches(TEST, "XMAS")?;
+		assert_eq!(18, matches.len());
+		// the XMAS reading right along the fifth row
+		assert!(matches.contains(&((4, 0), Direction::East)));
+		// and the one reading straight up the last column
+		assert!(matches.contains(&((9, 9), Direction::North)));
+		Ok(())
+	}
+
+	#[test]
+	fn test_find_x_mas() -> Result<()> {
+		let centers = find_x_mas(TEST)?;
+		assert_eq!(9, centers.len());
+		assert!(centers.contains(&(1, 2)));
+		Ok(())
+	}
 }
@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet};
 use std::ops::ControlFlow;
 use anyhow::*;
@@ -8,7 +9,7 @@ use nom::multi::{many1, separated_list1};
 use nom::sequence::{separated_pair, terminated};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-struct PageOrder {
+pub struct PageOrder {
 	first: u64,
 	second: u64,
 }
@@ -42,33 +43,63 @@ fn parse_page_update_line(input: &str) -> IResult<&str, Vec<u64>> {
 	terminated(parse_page_updates, opt(line_ending))(input)
 }
 
-fn parse(input: &str) -> (Vec<PageOrder>, Vec<Vec<u64>>) {
+// page ordering rules, then the list of updates to print
+pub type PrintQueue = (Vec<PageOrder>, Vec<Vec<u64>>);
+
+pub fn parse_input(input: &str) -> Result<PrintQueue> {
 	let (_, result) = all_consuming(separated_pair(
 		many1(parse_page_ordering_line),
 		line_ending,
 		many1(parse_page_update_line)
-	))(input).finish().unwrap();
-	result
+	))(input).finish()
+		.map_err(|err| anyhow!("could not parse input at {:?}: {:?}", err.input.lines().next().unwrap_or_default(), err.code))?;
+	Ok(result)
 }
 
+pub struct Day05;
+
 pub fn part1(input: &str) -> Result<u64> {
-	let (page_orders, printed_updates) = parse(input);
-
-	// we have a set of page ordering rules, where if both the first and second values are present,
-	// then the second value must come after the first in the printed updates
-	//
-	// for each page number, generate a set of associated page numbers which, if the first page is
-	// present in the printed updates, then seeing any of the numbers in the set means this update
-	// contains a pair of pages in the wrong order
-	let mut invalid_orders: BTreeMap<u64, BTreeSet<u64>> = BTreeMap::new();
-	page_orders.into_iter().for_each(|page_order| {
+	Ok(part1_parsed(&parse_input(input)?))
+}
+
+// both answers from a single parse of the input
+pub fn solve_both(input: &str) -> Result<(String, String)> {
+	let parsed = parse_input(input)?;
+
+	Ok((part1_parsed(&parsed).to_string(), part2_parsed(&parsed).to_string()))
+}
+
+// page numbers mapped to the set of pages on the other side of every rule they appear first in
+pub type OrderGraph = BTreeMap<u64, BTreeSet<u64>>;
+
+// we have a set of page ordering rules, where if both the first and second values are present,
+// then the second value must come after the first in the printed updates
+//
+// the forward graph maps each page to the pages that must come after it, the inverted graph maps
+// each page to the pages that must come before it; if the first page is present in the printed
+// updates, then seeing any of the pages in its inverted set afterwards means this update contains
+// a pair of pages in the wrong order
+pub fn build_order_graph(orders: &[PageOrder]) -> (OrderGraph, OrderGraph) {
+	let mut valid_orders = BTreeMap::new();
+	let mut invalid_orders = BTreeMap::new();
+	orders.iter().for_each(|page_order| {
+		valid_orders.entry(page_order.first)
+			.or_insert(BTreeSet::new())
+			.insert(page_order.second);
+
 		let invalid_order = page_order.invert();
 		invalid_orders.entry(invalid_order.first)
 			.or_insert(BTreeSet::new())
 			.insert(invalid_order.second);
 	});
 
-	let result = printed_updates.into_iter().filter_map(|update_list| {
+	(valid_orders, invalid_orders)
+}
+
+pub fn part1_parsed((page_orders, printed_updates): &PrintQueue) -> u64 {
+	let (_, invalid_orders) = build_order_graph(page_orders);
+
+	printed_updates.iter().filter_map(|update_list| {
 		let mut invalid_watchlist = BTreeSet::new();
 		let valid_update_list = update_list.iter().try_for_each(|updated_page| {
 			if invalid_watchlist.contains(updated_page) {
@@ -87,28 +118,30 @@ pub fn part1(input: &str) -> Result<u64> {
 		} else {
 			None
 		}
-	}).sum();
-
-	Ok(result)
+	}).sum()
 }
 
 pub fn part2(input: &str) -> Result<u64> {
-	let (page_orders, printed_updates) = parse(input);
+	Ok(part2_parsed(&parse_input(input)?))
+}
 
-	let mut valid_orders = BTreeMap::new();
-	let mut invalid_orders = BTreeMap::new();
-	page_orders.into_iter().for_each(|page_order| {
-		valid_orders.entry(page_order.first)
-			.or_insert(BTreeSet::new())
-			.insert(page_order.second);
+pub fn part2_parsed((page_orders, printed_updates): &PrintQueue) -> u64 {
+	corrected_updates(page_orders, printed_updates).into_iter().map(|corrected_list| {
+		let len = corrected_list.len();
+		corrected_list[(len - 1) / 2]
+	}).sum()
+}
 
-		let invalid_order = page_order.invert();
-		invalid_orders.entry(invalid_order.first)
-			.or_insert(BTreeSet::new())
-			.insert(invalid_order.second);
-	});
+pub fn part2_corrected(input: &str) -> Result<Vec<Vec<u64>>> {
+	let (page_orders, printed_updates) = parse_input(input)?;
 
-	let result = printed_updates.into_iter().filter_map(|update_list| {
+	Ok(corrected_updates(&page_orders, &printed_updates))
+}
+
+fn corrected_updates(page_orders: &[PageOrder], printed_updates: &[Vec<u64>]) -> Vec<Vec<u64>> {
+	let (valid_orders, invalid_orders) = build_order_graph(page_orders);
+
+	printed_updates.iter().filter_map(|update_list| {
 		let mut invalid_watchlist = BTreeSet::new();
 		let invalid_update_list = update_list.iter().fold(false, |mut invalid_order, updated_page| {
 			if invalid_watchlist.contains(updated_page) {
@@ -122,23 +155,22 @@ pub fn part2(input: &str) -> Result<u64> {
 		});
 
 		if invalid_update_list {
-			let mut list_priority = BTreeMap::new();
-			let update_set = BTreeSet::from_iter(update_list);
-			update_set.iter().for_each(|&updated_page| {
-				let count = valid_orders.get(&updated_page).map(|valid_updates|
-					update_set.intersection(valid_updates).count()
-				).unwrap_or(0);
-				list_priority.insert(count, updated_page);
+			// the rules cover every pair of pages within an update, so they can be used directly as a comparator
+			let mut update_list = update_list.clone();
+			update_list.sort_by(|page_one, page_two| {
+				if valid_orders.get(page_one).is_some_and(|after| after.contains(page_two)) {
+					Ordering::Less
+				} else if valid_orders.get(page_two).is_some_and(|after| after.contains(page_one)) {
+					Ordering::Greater
+				} else {
+					Ordering::Equal
+				}
 			});
-
-			let len = list_priority.len();
-			Some(list_priority[&((len - 1) / 2)])
+			Some(update_list)
 		} else {
 			None
 		}
-	}).sum();
-
-	Ok(result)
+	}).collect()
 }
 
 #[cfg(test)]
@@ -185,4 +217,52 @@ mod tests {
 		assert_eq!(123, part2(TEST)?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_part_two_corrected() -> Result<()> {
+		let (page_orders, _) = parse_input(TEST)?;
+		let corrected = part2_corrected(TEST)?;
+		assert_eq!(vec![
+			vec![97, 75, 47, 61, 53],
+			vec![61, 29, 13],
+			vec![97, 75, 47, 29, 13],
+		], corrected);
+
+		// every rule where both pages are present must have the first page printed before the second
+		assert!(corrected.iter().all(|update_list| {
+			page_orders.iter().all(|PageOrder { first, second }| {
+				let first_ix = update_list.iter().position(|page| page == first);
+				let second_ix = update_list.iter().position(|page| page == second);
+				match (first_ix, second_ix) {
+					(Some(first_ix), Some(second_ix)) => first_ix < second_ix,
+					_ => true
+				}
+			})
+		}));
+		Ok(())
+	}
+
+	#[test]
+	fn test_build_order_graph() -> Result<()> {
+		let (page_orders, _) = parse_input(TEST)?;
+		let (valid_orders, invalid_orders) = build_order_graph(&page_orders);
+
+		assert_eq!(BTreeSet::from([13, 29, 47, 53, 61, 75]), valid_orders[&97]);
+		// nothing has to come before 97
+		assert!(!invalid_orders.contains_key(&97));
+		assert_eq!(BTreeSet::from([47, 53, 61, 75, 97]), invalid_orders[&29]);
+		Ok(())
+	}
+
+	#[test]
+	fn test_solve_both() -> Result<()> {
+		assert_eq!((part1(TEST)?.to_string(), part2(TEST)?.to_string()), solve_both(TEST)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_truncated_input() {
+		assert!(part1("47|53\n97|").is_err());
+		assert!(part2("47|53\n97|").is_err());
+	}
 }
@@ -7,6 +7,9 @@ use nom::combinator::{all_consuming, opt, value};
 use nom::IResult;
 use nom::multi::many1;
 use nom::sequence::terminated;
+use crate::direction::Direction;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum MapCell {
@@ -15,36 +18,15 @@ enum MapCell {
 	Guard(Direction),
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-enum Direction {
-	North,
-	East,
-	South,
-	West,
-}
-impl Direction {
-	pub fn get_offset(&self) -> (isize, isize) {
-		match self {
-			Direction::North => (-1, 0),
-			Direction::East => (0, 1),
-			Direction::South => (1, 0),
-			Direction::West => (0, -1),
-		}
-	}
-
-	pub fn offset_from(&self, (row, col): (impl TryInto<isize>, impl TryInto<isize>)) -> Option<(isize, isize)> {
-		let row_isize = row.try_into().ok()?;
-		let col_isize = col.try_into().ok()?;
-		let (row_offset, col_offset) = self.get_offset();
-		Some((row_isize + row_offset, col_isize + col_offset))
-	}
-
-	pub fn rotate(&self) -> Self {
-		match self {
-			Direction::North => Direction::East,
-			Direction::East => Direction::South,
-			Direction::South => Direction::West,
-			Direction::West => Direction::North,
+impl From<MapCell> for char {
+	fn from(value: MapCell) -> Self {
+		match value {
+			MapCell::Empty => '.',
+			MapCell::Obstacle => '#',
+			MapCell::Guard(Direction::North) => '^',
+			MapCell::Guard(Direction::East) => '>',
+			MapCell::Guard(Direction::South) => 'v',
+			MapCell::Guard(Direction::West) => '<',
 		}
 	}
 }
@@ -72,94 +54,136 @@ fn parse(input: &str) -> Grid<MapCell> {
 	result.into()
 }
 
-pub fn part1(input: &str) -> Result<u64> {
-	let map = parse(input);
-
-	let (mut guard_position, mut guard_direction) = map.indexed_iter().find_map(|((x, y), &cell)| {
+fn find_guard(map: &Grid<MapCell>) -> Result<((isize, isize), Direction)> {
+	let mut guards = map.indexed_iter().filter_map(|((x, y), &cell)| {
 		if let MapCell::Guard(dir) = cell {
 			Some(((isize::try_from(x).unwrap(), isize::try_from(y).unwrap()), dir))
 		} else {
 			None
 		}
-	}).unwrap();
+	});
 
-	let mut visited_positions = AHashSet::new();
-	visited_positions.insert(guard_position);
-	let (mut next_row, mut next_col) = guard_direction.offset_from(guard_position).unwrap();
+	let Some(guard) = guards.next() else {
+		bail!("no guard found");
+	};
+	if guards.next().is_some() {
+		bail!("more than one guard found");
+	}
+	Ok(guard)
+}
 
-	while let Some(&next_cell) = map.get(next_row, next_col) {
-		// don't ever move or clear original guard cell, so only check obstacle vs non-obstacle
-		if next_cell == MapCell::Obstacle {
-			guard_direction = guard_direction.rotate();
-		} else {
-			guard_position = (next_row, next_col);
-			visited_positions.insert(guard_position);
-		}
+// steps a guard around the map, one move at a time
+pub struct GuardSim {
+	map: Grid<MapCell>,
+	pos: (isize, isize),
+	dir: Direction,
+}
 
-		(next_row, next_col) = guard_direction.offset_from(guard_position).unwrap();
+impl GuardSim {
+	fn new(map: Grid<MapCell>) -> Result<Self> {
+		let (pos, dir) = find_guard(&map)?;
+		Ok(Self { map, pos, dir })
 	}
 
-	Ok(visited_positions.len() as u64)
+	pub fn from_input(input: &str) -> Result<Self> {
+		Self::new(parse(input))
+	}
+
+	pub fn position(&self) -> (isize, isize) {
+		self.pos
+	}
+
+	pub fn direction(&self) -> Direction {
+		self.dir
+	}
+
+	// turn right until there's no obstacle ahead, then move forward once,
+	// returning the new position or None if that move leaves the map
+	pub fn step(&mut self) -> Option<(isize, isize)> {
+		for _ in 0..4 {
+			let (next_row, next_col) = self.dir.offset_from(self.pos)?;
+			// don't ever move or clear original guard cell, so only check obstacle vs non-obstacle
+			match self.map.get(next_row, next_col) {
+				None => return None,
+				Some(MapCell::Obstacle) => self.dir = self.dir.turn_right(),
+				Some(_) => {
+					self.pos = (next_row, next_col);
+					return Some(self.pos);
+				},
+			}
+		}
+		// boxed in on every side, so the guard can only stay put
+		Some(self.pos)
+	}
 }
 
-pub fn part2(input: &str) -> Result<u64> {
-	let map = parse(input);
+impl Iterator for GuardSim {
+	type Item = (isize, isize);
 
-	let (mut guard_position, mut guard_direction) = map.indexed_iter().find_map(|((x, y), &cell)| {
-		if let MapCell::Guard(dir) = cell {
-			Some(((isize::try_from(x).unwrap(), isize::try_from(y).unwrap()), dir))
-		} else {
-			None
+	fn next(&mut self) -> Option<Self::Item> {
+		self.step()
+	}
+}
+
+pub struct Day06;
+
+pub fn part1(input: &str) -> Result<u64> {
+	let mut guard = GuardSim::from_input(input)?;
+
+	let mut visited_positions = AHashSet::from([guard.position()]);
+	let mut visited_states = AHashSet::from([(guard.position(), guard.direction())]);
+	while let Some(guard_position) = guard.step() {
+		visited_positions.insert(guard_position);
+		// seeing the same position and direction twice means the guard is stuck walking in a loop
+		if !visited_states.insert((guard_position, guard.direction())) {
+			bail!("guard loops without leaving map");
 		}
-	}).unwrap();
+	}
 
-	let mut looping_positions = 0;
-	let mut added_obstacle_positions = AHashSet::new();
-	fn detect_loop(map: Grid<MapCell>, mut curr_pos: (isize, isize), mut curr_dir: Direction, mut visited_states: AHashSet<((isize, isize), Direction)>) -> bool {
-		let (mut next_row, mut next_col) = curr_dir.offset_from(curr_pos).unwrap();
-		visited_states.insert((curr_pos, curr_dir));
+	Ok(visited_positions.len() as u64)
+}
 
-		while let Some(&next_cell) = map.get(next_row, next_col) {
-			// don't ever move or clear original guard cell, so only check obstacle vs non-obstacle
-			if next_cell == MapCell::Obstacle {
-				curr_dir = curr_dir.rotate();
-			} else {
-				curr_pos = (next_row, next_col);
-			}
+pub fn part2(input: &str) -> Result<u64> {
+	let map = parse(input);
+	let mut guard = GuardSim::new(map.clone())?;
 
-			let looping = !visited_states.insert((curr_pos, curr_dir));
+	fn detect_loop(mut guard: GuardSim, mut visited_states: AHashSet<((isize, isize), Direction)>) -> bool {
+		visited_states.insert((guard.position(), guard.direction()));
+		while let Some(curr_pos) = guard.step() {
+			let looping = !visited_states.insert((curr_pos, guard.direction()));
 			if looping { return true; }
-
-			(next_row, next_col) = curr_dir.offset_from(curr_pos).unwrap();
 		}
 		false
 	}
 
-	let mut visited_states = AHashSet::new();
-	visited_states.insert((guard_position, guard_direction));
-	let (mut next_row, mut next_col) = guard_direction.offset_from(guard_position).unwrap();
-	while let Some(&next_cell) = map.get(next_row, next_col) {
-		// don't ever move or clear original guard cell, so only check obstacle vs non-obstacle
-		if next_cell == MapCell::Obstacle {
-			guard_direction = guard_direction.rotate();
-		} else {
-			// if the next cell is empty, and we haven't tried putting an obstacle there yet, try that
-			if !added_obstacle_positions.contains(&(next_row, next_col)) && next_cell == MapCell::Empty {
-				added_obstacle_positions.insert((next_row, next_col));
-				let mut modified_map = map.clone();
-				*modified_map.get_mut(next_row, next_col).unwrap() = MapCell::Obstacle;
-				if detect_loop(modified_map, guard_position, guard_direction, visited_states.clone()) {
-					looping_positions += 1;
-				}
-			}
-			guard_position = (next_row, next_col);
+	let mut added_obstacle_positions = AHashSet::new();
+	let mut candidates = Vec::new();
+	// states in the order the guard visits them, each candidate only keeps how far along it was
+	let mut path = vec![(guard.position(), guard.direction())];
+	let mut guard_position = guard.position();
+	while let Some(next_position) = guard.step() {
+		// if the guard moved onto an empty cell, and we haven't tried putting an obstacle there yet, try that
+		// with the guard starting from just before it, already facing it
+		if map.get(next_position.0, next_position.1) == Some(&MapCell::Empty) && added_obstacle_positions.insert(next_position) {
+			candidates.push((next_position, guard_position, guard.direction(), path.len()));
 		}
-		visited_states.insert((guard_position, guard_direction));
-
-		(next_row, next_col) = guard_direction.offset_from(guard_position).unwrap();
+		guard_position = next_position;
+		path.push((guard_position, guard.direction()));
 	}
 
-	Ok(looping_positions)
+	// every candidate gets its own copy of the map and rebuilds the states visited before it, so they can be checked independently
+	let is_looping = |((obstacle_row, obstacle_col), pos, dir, step_index): (_, _, _, usize)| {
+		let mut modified_map = map.clone();
+		*modified_map.get_mut(obstacle_row, obstacle_col).unwrap() = MapCell::Obstacle;
+		let visited_states = path[..step_index].iter().copied().collect();
+		detect_loop(GuardSim { map: modified_map, pos, dir }, visited_states)
+	};
+	#[cfg(feature = "parallel")]
+	let looping_positions = candidates.into_par_iter().map(is_looping).filter(|&looping| looping).count();
+	#[cfg(not(feature = "parallel"))]
+	let looping_positions = candidates.into_iter().map(is_looping).filter(|&looping| looping).count();
+
+	Ok(looping_positions as u64)
 }
 
 #[cfg(test)]
@@ -188,4 +212,56 @@ mod tests {
 		assert_eq!(6, part2(TEST)?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_guard_sim() -> Result<()> {
+		let guard = GuardSim::from_input(TEST)?;
+		let start = guard.position();
+		assert_eq!((6, 4), start);
+
+		let visited = guard.chain([start]).collect::<AHashSet<_>>();
+		assert_eq!(41, visited.len());
+		Ok(())
+	}
+
+	#[test]
+	fn test_guard_sim_turns() -> Result<()> {
+		// blocked ahead and to the right, so the first step turns twice before moving
+		let mut guard = GuardSim::from_input(".#.\n.^#\n...")?;
+		assert_eq!(Some((2, 1)), guard.step());
+		assert_eq!(Direction::South, guard.direction());
+		assert_eq!(None, guard.step());
+		Ok(())
+	}
+
+	#[test]
+	fn test_part_one_enclosed() -> Result<()> {
+		assert!(part1(".#.
+#^#
+.#.").is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_part_one_loop() {
+		// the guard walks around the four obstacles forever
+		let result = part1(".#...
+....#
+.^...
+#....
+...#.");
+		assert_eq!("guard loops without leaving map", result.unwrap_err().to_string());
+	}
+
+	#[test]
+	fn test_no_guard() {
+		assert!(part1("...\n...\n...").is_err());
+		assert!(part2("...\n...\n...").is_err());
+	}
+
+	#[test]
+	fn test_multiple_guards() {
+		assert!(part1("^..\n...\n..<").is_err());
+		assert!(part2("^..\n...\n..<").is_err());
+	}
 }
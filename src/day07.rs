@@ -1,12 +1,12 @@
-use std::collections::VecDeque;
 use anyhow::*;
-use itertools::{repeat_n, Itertools};
 use nom::bytes::complete::tag;
 use nom::character::complete::{char, digit1, line_ending};
 use nom::combinator::{all_consuming, map, map_res, opt};
 use nom::{Finish, IResult};
 use nom::multi::{many1, separated_list1};
 use nom::sequence::{separated_pair, terminated};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 struct Equation {
@@ -15,17 +15,27 @@ struct Equation {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Operator {
+pub enum Operator {
 	Addition,
-	Multiplication
+	Multiplication,
+	Concatenation
 }
 impl Operator {
-	pub const fn values() -> [Operator; 2] {
+	pub const fn values() -> [Operator; 3] {
 		[
 			Operator::Addition,
-			Operator::Multiplication
+			Operator::Multiplication,
+			Operator::Concatenation
 		]
 	}
+
+	pub fn apply(&self, lhs: u64, rhs: u64) -> u64 {
+		match self {
+			Operator::Addition => lhs + rhs,
+			Operator::Multiplication => lhs * rhs,
+			Operator::Concatenation => lhs * 10u64.pow(rhs.checked_ilog10().unwrap_or(0) + 1) + rhs
+		}
+	}
 }
 
 fn parse_u64(input: &str) -> IResult<&str, u64> {
@@ -40,61 +50,53 @@ fn parse_equation(input: &str) -> IResult<&str, Equation> {
 	), |(test_val, numbers)| Equation { test_val, numbers })(input)
 }
 
-fn parse(input: &str) -> Vec<Equation> {
-	let (_, result) = all_consuming(many1(parse_equation))(input).finish().unwrap();
-	result
+fn parse(input: &str) -> Result<Vec<Equation>> {
+	let (_, result) = all_consuming(many1(parse_equation))(input).finish()
+		.map_err(|err| anyhow!("could not parse input at {:?}: {:?}", err.input.lines().next().unwrap_or_default(), err.code))?;
+	Ok(result)
 }
 
-pub fn part1(input: &str) -> Result<u64> {
-	let equations = parse(input);
-
-	let result = equations.into_iter().filter_map(|Equation { test_val, numbers }| {
-		let mut numbers = VecDeque::from_iter(numbers.clone());
-		let starting_value = numbers.pop_front().unwrap();
-		let numbers = numbers;
-		let found_good_operations = repeat_n(Operator::values().into_iter(), numbers.len()).multi_cartesian_product().into_iter().find(|operators| {
-			let total_value = operators.into_iter().zip_eq(numbers.clone()).fold(starting_value, |total, (op, next_val)| {
-				match op {
-					Operator::Addition => total + next_val,
-					Operator::Multiplication => total * next_val
-				}
-			});
-			total_value == test_val
-		});
-
-		if let Some(_) = found_good_operations {
-			Some(test_val)
-		} else {
-			None
-		}
-	}).sum();
+pub struct Day07;
 
-	Ok(result)
+pub fn part1(input: &str) -> Result<u64> {
+	solve(input, &[Operator::Addition, Operator::Multiplication])
 }
 
 pub fn part2(input: &str) -> Result<u64> {
-	let equations = parse(input);
+	solve(input, &Operator::values())
+}
+
+pub fn solve(input: &str, operators: &[Operator]) -> Result<u64> {
+	let equations = parse(input)?;
 
-	fn process_operation_recursive(value: u64, numbers: &[u64], target: u64) -> bool {
-		if let Some((next_number, numbers)) = numbers.split_first() {
-			process_operation_recursive(value + next_number, numbers, target)
-				|| process_operation_recursive(value * next_number, numbers, target)
-				|| process_operation_recursive(value * 10u64.pow(next_number.ilog10() + 1) + next_number, numbers, target)
+	fn process_operation_recursive(value: u64, numbers: &[u64], target: u64, operators: &[Operator]) -> bool {
+		// no operator makes the running total smaller (short of multiplying by zero), so once past the target it stays past it
+		if value > target && !numbers.contains(&0) {
+			return false;
+		}
+		if let Some((&next_number, numbers)) = numbers.split_first() {
+			operators.iter().any(|op| process_operation_recursive(op.apply(value, next_number), numbers, target, operators))
 		} else {
 			value == target
 		}
 	}
 
-	let result = equations.into_iter().filter_map(|Equation { test_val, numbers }| {
+	let check_equation = |Equation { test_val, numbers }: Equation| {
 		let (&starting_value, numbers) = numbers.as_slice().split_first().unwrap();
-		let found_good_operations = process_operation_recursive(starting_value, numbers, test_val);
+		let found_good_operations = process_operation_recursive(starting_value, numbers, test_val, operators);
 
 		if found_good_operations {
 			Some(test_val)
 		} else {
 			None
 		}
-	}).sum();
+	};
+	// every equation is checked independently, the work per equation grows exponentially
+	// with its length so inputs with long equations benefit the most
+	#[cfg(feature = "parallel")]
+	let result = equations.into_par_iter().filter_map(check_equation).sum();
+	#[cfg(not(feature = "parallel"))]
+	let result = equations.into_iter().filter_map(check_equation).sum();
 
 	Ok(result)
 }
@@ -124,4 +126,29 @@ mod tests {
 		assert_eq!(11387, part2(TEST)?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_addition_and_concatenation() -> Result<()> {
+		// only 156: 15 || 6 and 192: 17 || 8 + 14 work without multiplication
+		assert_eq!(156 + 192, solve(TEST, &[Operator::Addition, Operator::Concatenation])?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_truncated_input() {
+		assert!(part1("190: 10 19\n3267:").is_err());
+		assert!(part2("190: 10 19\n3267:").is_err());
+	}
+
+	#[test]
+	fn test_long_equation() -> Result<()> {
+		// every combination of operators over 40 numbers is far too many to try, but every one of them overshoots quickly
+		let long_equation = format!("5: {}", ["2"; 40].join(" "));
+		assert_eq!(0, part1(&long_equation)?);
+		assert_eq!(0, part2(&long_equation)?);
+
+		// multiplying by zero brings an overshooting total back down
+		assert_eq!(3, part1("3: 5 2 0 3")?);
+		Ok(())
+	}
 }
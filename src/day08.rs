@@ -43,47 +43,39 @@ fn parse(input: &str) -> Vec<Vec<MapCell>> {
 	result
 }
 
+pub struct Day08;
+
 pub fn part1(input: &str) -> Result<u64> {
-	let map: Grid<_> = parse(input).into();
+	count_antinodes(input, false)
+}
 
-	let mut frequency_coordinate_map = AHashMap::new();
-	map.indexed_iter().for_each(|(coords, &cell)| {
-		if let MapCell::Antenna { frequency } = cell {
-			frequency_coordinate_map.entry(frequency)
-				.or_insert(Vec::new())
-				.push(coords);
-		}
-	});
-	let frequency_coordinate_map = frequency_coordinate_map;
+pub fn part2(input: &str) -> Result<u64> {
+	count_antinodes(input, true)
+}
 
-	let mut antinode_coords = AHashSet::new();
-	frequency_coordinate_map.into_iter().for_each(|(_frequency, coordinate_list)| {
-		coordinate_list.into_iter().combinations(2).for_each(|x| {
-			let (&(first_row, first_col), _) = x.split_first().unwrap();
-			let (&(second_row, second_col), _) = x.split_last().unwrap();
-			let (first_row, first_col) = (isize::try_from(first_row).unwrap(), isize::try_from(first_col).unwrap());
-			let (second_row, second_col) = (isize::try_from(second_row).unwrap(), isize::try_from(second_col).unwrap());
-			let (first_antinode_row, first_antinode_col) = ((first_row - second_row) + first_row, (first_col - second_col) + first_col);
-			let (second_antinode_row, second_antinode_col) = ((second_row - first_row) + second_row, (second_col - first_col) + second_col);
-			if let Some(_) = map.get(first_antinode_row, first_antinode_col) {
-				antinode_coords.insert((first_antinode_row, first_antinode_col));
-			}
-			if let Some(_) = map.get(second_antinode_row, second_antinode_col) {
-				antinode_coords.insert((second_antinode_row, second_antinode_col));
-			}
-		})
-	});
+pub fn count_antinodes(input: &str, harmonics: bool) -> Result<u64> {
+	Ok(antinode_positions(input, harmonics)?.len() as u64)
+}
 
-	Ok(antinode_coords.len() as u64)
+// treats antennas as the same frequency if they only differ by case, so A and a pair up
+pub fn count_antinodes_case_insensitive(input: &str, harmonics: bool) -> Result<u64> {
+	Ok(grouped_antinode_positions(input, harmonics, |frequency| frequency.to_ascii_lowercase())?.len() as u64)
 }
 
-pub fn part2(input: &str) -> Result<u64> {
+// antinodes on top of antennas are still antinodes
+// frequencies are case sensitive, so antennas A and a never pair up
+pub fn antinode_positions(input: &str, harmonics: bool) -> Result<AHashSet<(isize, isize)>> {
+	grouped_antinode_positions(input, harmonics, |frequency| frequency)
+}
+
+// antennas pair up with any others that have the same frequency key
+fn grouped_antinode_positions(input: &str, harmonics: bool, frequency_key: fn(char) -> char) -> Result<AHashSet<(isize, isize)>> {
 	let map: Grid<_> = parse(input).into();
 
 	let mut frequency_coordinate_map = AHashMap::new();
 	map.indexed_iter().for_each(|(coords, &cell)| {
 		if let MapCell::Antenna { frequency } = cell {
-			frequency_coordinate_map.entry(frequency)
+			frequency_coordinate_map.entry(frequency_key(frequency))
 				.or_insert(Vec::new())
 				.push(coords);
 		}
@@ -99,6 +91,25 @@ pub fn part2(input: &str) -> Result<u64> {
 			let (second_row, second_col) = (isize::try_from(second_row).unwrap(), isize::try_from(second_col).unwrap());
 			let (first_row_delta, first_col_delta) = (first_row - second_row, first_col - second_col);
 			let (second_row_delta, second_col_delta) = (second_row - first_row, second_col - first_col);
+
+			if !harmonics {
+				// a single antinode on the far side of each antenna
+				let (first_antinode_row, first_antinode_col) = (first_row + first_row_delta, first_col + first_col_delta);
+				let (second_antinode_row, second_antinode_col) = (second_row + second_row_delta, second_col + second_col_delta);
+				if map.get(first_antinode_row, first_antinode_col).is_some() {
+					antinode_coords.insert((first_antinode_row, first_antinode_col));
+				}
+				if map.get(second_antinode_row, second_antinode_col).is_some() {
+					antinode_coords.insert((second_antinode_row, second_antinode_col));
+				}
+				return;
+			}
+
+			if (first_row_delta, first_col_delta) == (0, 0) {
+				// coincident antennas would never step off the map, the only antinode is the antenna itself
+				antinode_coords.insert((first_row, first_col));
+				return;
+			}
 			let first_antinode_iter = successors(Some((first_row, first_col)), |&(row, col)| {
 				Some((row + first_row_delta, col + first_col_delta))
 			});
@@ -112,7 +123,7 @@ pub fn part2(input: &str) -> Result<u64> {
 		})
 	});
 
-	Ok(antinode_coords.len() as u64)
+	Ok(antinode_coords)
 }
 
 #[cfg(test)]
@@ -143,4 +154,53 @@ mod tests {
 		assert_eq!(34, part2(TEST)?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_part_two_positions() -> Result<()> {
+		let positions = antinode_positions(TEST, true)?;
+		assert_eq!(34, positions.len());
+		assert!(positions.contains(&(0, 0)));
+		assert!(positions.contains(&(0, 11)));
+		assert!(positions.contains(&(11, 11)));
+		assert!(!positions.contains(&(11, 0)));
+		// antennas with a partner of the same frequency are antinodes themselves
+		assert!(positions.contains(&(5, 6)));
+		Ok(())
+	}
+
+	#[test]
+	fn test_shared_row_antennas() -> Result<()> {
+		const SAME_ROW: &str = "a..a...";
+		assert_eq!(1, part1(SAME_ROW)?);
+		assert_eq!(3, part2(SAME_ROW)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_shared_column_antennas() -> Result<()> {
+		const SAME_COL: &str = "a
+.
+.
+a
+.
+.
+.";
+		assert_eq!(1, part1(SAME_COL)?);
+		assert_eq!(3, part2(SAME_COL)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_mixed_case_antennas() -> Result<()> {
+		const MIXED_CASE: &str = "A..a...
+.......
+B..b..B";
+		// only the two B antennas share a frequency
+		assert_eq!(0, part1(MIXED_CASE)?);
+		assert_eq!(2, part2(MIXED_CASE)?);
+
+		assert_eq!(3, count_antinodes_case_insensitive(MIXED_CASE, false)?);
+		assert_eq!(6, count_antinodes_case_insensitive(MIXED_CASE, true)?);
+		Ok(())
+	}
 }
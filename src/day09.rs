@@ -1,5 +1,5 @@
 use std::collections::{BTreeMap, BTreeSet};
-use std::iter::repeat;
+use std::iter::{repeat, repeat_n};
 use anyhow::*;
 use nom::bytes::complete::take;
 use nom::character::complete::line_ending;
@@ -9,7 +9,7 @@ use nom::multi::many1;
 use nom::sequence::terminated;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum DiskContent {
+pub enum DiskContent {
 	File{ id: u64 },
 	FreeSpace
 }
@@ -29,9 +29,41 @@ fn parse(input: &str) -> Vec<u64> {
 	result
 }
 
+pub struct Day09;
+
 pub fn part1(input: &str) -> Result<u64> {
 	let disk_map = parse(input);
 
+	// expand to one entry per block, None for free space
+	let mut blocks = disk_map.into_iter().enumerate().flat_map(|(ix, length)| {
+		let content = (ix % 2 == 0).then_some(ix as u64 / 2);
+		repeat_n(content, length as usize)
+	}).collect::<Vec<_>>();
+
+	// walk in from both ends, moving the last file block into the first free block
+	let mut checksum = 0;
+	let (mut left, mut right) = (0, blocks.len());
+	while left < right {
+		if blocks[left].is_none() {
+			right -= 1;
+			while left < right && blocks[right].is_none() {
+				right -= 1;
+			}
+			blocks.swap(left, right);
+		}
+		if let Some(id) = blocks[left] {
+			checksum += left as u64 * id;
+		}
+		left += 1;
+	}
+
+	Ok(checksum)
+}
+
+// original implementation, tracking free and filled blocks in ordered collections
+pub fn part1_btree(input: &str) -> Result<u64> {
+	let disk_map = parse(input);
+
 	let disk_map = {
 		let mut next_id = 0;
 		disk_map.into_iter().enumerate().map(move |(ix, length)| {
@@ -56,7 +88,11 @@ pub fn part1(input: &str) -> Result<u64> {
 		(free_set, fill_map, next_ix)
 	});
 
-	while filled_indices.last_key_value().unwrap().0 > free_indices.first().unwrap() {
+	// a disk map without any free space (or without any files) is already compacted
+	while let (Some((&max_filled_index, _)), Some(&min_free_space)) = (filled_indices.last_key_value(), free_indices.first()) {
+		if max_filled_index < min_free_space {
+			break;
+		}
 		let (max_filled_index, max_filled_value) = filled_indices.pop_last().unwrap();
 		let min_free_space = free_indices.pop_first().unwrap();
 
@@ -77,6 +113,32 @@ struct DiskMapPartTwo {
 }
 
 pub fn part2(input: &str) -> Result<u64> {
+	let (file_handles, _) = compact_files(input);
+
+	let checksum = file_handles.into_iter().fold(0, |sum, (id, mapping)| {
+		let range = mapping.start..(mapping.start + mapping.length);
+		let range_sum: u64 = range.sum();
+		sum + (range_sum * id)
+	});
+
+	Ok(checksum)
+}
+
+// every block on the disk after part 2's compaction
+pub fn final_layout(input: &str) -> Result<Vec<DiskContent>> {
+	let (file_handles, free_blocks) = compact_files(input);
+
+	let mut layout = file_handles.into_values().map(|mapping| (mapping.start, mapping.length, mapping.content))
+		.chain(free_blocks.into_iter().map(|(start, length)| (start, length, DiskContent::FreeSpace)))
+		.collect::<Vec<_>>();
+	layout.sort_unstable_by_key(|&(start, _, _)| start);
+
+	Ok(layout.into_iter().flat_map(|(_, length, content)| repeat_n(content, length as usize)).collect())
+}
+
+// moves whole files into the leftmost gap that fits them, returning the files by id and the
+// free space by start index, with adjacent free blocks merged together
+fn compact_files(input: &str) -> (BTreeMap<u64, DiskMapPartTwo>, BTreeMap<u64, u64>) {
 	let disk_map = parse(input);
 
 	let disk_map = {
@@ -108,30 +170,42 @@ pub fn part2(input: &str) -> Result<u64> {
 		(files, free_map)
 	});
 
-	// println!("file_handles: {:?}", file_handles);
 	for (_, file_mapping) in file_handles.iter_mut().rev() {
 		let file_length = file_mapping.length;
 		if let Some((&free_start, &free_length)) = free_blocks.iter().find(|(_, &length)| length >= file_length) {
 			if free_start < file_mapping.start {
 				free_blocks.remove(&free_start);
-				file_mapping.start = free_start;
-
 				let remaining_free_len = free_length - file_length;
 				if remaining_free_len > 0 {
 					free_blocks.insert(free_start + file_length, remaining_free_len);
 				}
+
+				// the space left behind is always to the right of every file still to be moved,
+				// so handing it back can't change where they end up
+				insert_free_block(&mut free_blocks, file_mapping.start, file_length);
+				file_mapping.start = free_start;
 			}
 		}
 	}
-	// println!("file_handles: {:?}", file_handles);
 
-	let checksum = file_handles.into_iter().fold(0, |sum, (id, mapping)| {
-		let range = mapping.start..(mapping.start + mapping.length);
-		let range_sum: u64 = range.sum();
-		sum + (range_sum * id)
-	});
+	(file_handles, free_blocks)
+}
 
-	Ok(checksum)
+fn insert_free_block(free_blocks: &mut BTreeMap<u64, u64>, mut start: u64, mut length: u64) {
+	if length == 0 {
+		return;
+	}
+	if let Some((&prev_start, &prev_length)) = free_blocks.range(..start).next_back() {
+		if prev_start + prev_length == start {
+			free_blocks.remove(&prev_start);
+			start = prev_start;
+			length += prev_length;
+		}
+	}
+	if let Some(next_length) = free_blocks.remove(&(start + length)) {
+		length += next_length;
+	}
+	free_blocks.insert(start, length);
 }
 
 #[cfg(test)]
@@ -151,4 +225,54 @@ mod tests {
 		assert_eq!(2858, part2(TEST)?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_part_one_btree() -> Result<()> {
+		assert_eq!(1928, part1_btree(TEST)?);
+		assert_eq!(part1_btree(TEST)?, part1(TEST)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_trailing_segment() -> Result<()> {
+		// 0..111....22222 compacts to 022111222......
+		assert_eq!(60, part1("12345")?);
+		assert_eq!(60, part1_btree("12345")?);
+		// no file fits in any gap to its left, so nothing moves
+		assert_eq!(132, part2("12345")?);
+
+		// a trailing zero-length free segment changes nothing
+		assert_eq!(60, part1("123450")?);
+		assert_eq!(60, part1_btree("123450")?);
+		assert_eq!(132, part2("123450")?);
+
+		// 0..111.... compacts to 0111......
+		assert_eq!(6, part1("1234")?);
+		assert_eq!(6, part1_btree("1234")?);
+		assert_eq!(0, part1("10")?);
+		assert_eq!(0, part1_btree("10")?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_final_layout() -> Result<()> {
+		let rendered = final_layout(TEST)?.into_iter().map(|content| match content {
+			DiskContent::File { id } => char::from_digit(id as u32, 10).unwrap(),
+			DiskContent::FreeSpace => '.',
+		}).collect::<String>();
+		assert_eq!("00992111777.44.333....5555.6666.....8888..", rendered);
+
+		// every run of free space in the layout is a single free block
+		let (_, free_blocks) = compact_files(TEST);
+		assert_eq!(vec![(11, 1), (14, 1), (18, 4), (26, 1), (31, 5), (40, 2)], free_blocks.into_iter().collect::<Vec<_>>());
+		Ok(())
+	}
+
+	#[test]
+	fn test_crlf_line_ending() -> Result<()> {
+		assert_eq!(1928, part1("2333133121414131402\r\n")?);
+		assert_eq!(1928, part1_btree("2333133121414131402\r\n")?);
+		assert_eq!(2858, part2("2333133121414131402\r\n")?);
+		Ok(())
+	}
 }
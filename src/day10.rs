@@ -1,147 +1,102 @@
-use std::collections::VecDeque;
 use ahash::AHashSet;
 use anyhow::*;
 use grid::Grid;
-use nom::bytes::complete::take;
-use nom::character::complete::line_ending;
-use nom::combinator::{all_consuming, map_res, opt};
-use nom::{Finish, IResult};
-use nom::multi::many1;
-use nom::sequence::terminated;
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-enum Direction {
-	North,
-	East,
-	South,
-	West,
+use crate::direction::Direction;
+use crate::grid_ext::SignedGridExt;
+use crate::grid_parse::parse_grid;
+
+fn parse(input: &str) -> Result<Grid<u64>> {
+	parse_grid(input, |chr| chr.to_digit(10).map(u64::from))
 }
-impl Direction {
-	pub const fn get_offset(&self) -> (isize, isize) {
-		match self {
-			Direction::North => (-1, 0),
-			Direction::East => (0, 1),
-			Direction::South => (1, 0),
-			Direction::West => (0, -1),
-		}
-	}
 
-	pub fn offset_from(&self, (row, col): (impl TryInto<isize>, impl TryInto<isize>)) -> Option<(isize, isize)> {
-		let row_isize = row.try_into().ok()?;
-		let col_isize = col.try_into().ok()?;
-		let (row_offset, col_offset) = self.get_offset();
-		Some((row_isize + row_offset, col_isize + col_offset))
-	}
+pub struct Day10;
 
-	pub const fn values() -> [Direction; 4] {
-		[
-			Direction::North,
-			Direction::East,
-			Direction::South,
-			Direction::West
-		]
-	}
-}
+pub fn part1(input: &str) -> Result<u64> {
+	let topographical_map = parse(input)?;
+	let score_map = scores(&topographical_map);
 
-fn parse_digit(input: &str) -> IResult<&str, u64> {
-	map_res(take(1u8), |dgt| u64::from_str_radix(dgt, 10))(input)
+	Ok(topographical_map.indexed_iter().filter(|&(_, &height)| height == 0).map(|(coords, _)| score_map[coords]).sum())
 }
 
-fn parse_line(input: &str) -> IResult<&str, Vec<u64>> {
-	terminated(many1(parse_digit), opt(line_ending))(input)
+// how many peaks every cell can reach, which is the score for cells at the trailheads
+pub fn score_grid(input: &str) -> Result<Grid<u64>> {
+	Ok(scores(&parse(input)?))
 }
 
-fn parse(input: &str) -> Vec<Vec<u64>> {
-	let (_, result) = all_consuming(many1(parse_line))(input).finish().unwrap();
-	result
+fn scores(topographical_map: &Grid<u64>) -> Grid<u64> {
+	let (peak_map, _) = peaks_and_ratings(topographical_map);
+
+	let (grid_rows, grid_cols) = peak_map.size();
+	let mut score_map = Grid::init(grid_rows, grid_cols, 0);
+	peak_map.indexed_iter().for_each(|(coords, peaks)| score_map[coords] = peaks.len() as u64);
+	score_map
 }
 
-pub fn part1(input: &str) -> Result<u64> {
-	let topographical_map: Grid<_> = parse(input).into();
-
-	let mut score_map: Grid<u64> = {
-		let (grid_rows, grid_cols) = topographical_map.size();
-		Grid::init(grid_rows, grid_cols, 0)
-	};
-	topographical_map.indexed_iter().filter_map(|(coords, &height)| {
-		if height == 9 { Some(coords) } else { None }
-	}).for_each(|(row, col)| {
-		let mut nodes_to_process = VecDeque::new();
-		nodes_to_process.push_back((row as isize, col as isize));
-		let mut can_reach_this_peak = AHashSet::new();
-
-		while let Some((curr_row, curr_col)) = nodes_to_process.pop_front() {
-			can_reach_this_peak.insert((curr_row, curr_col));
-
-			if let Some(&height) = topographical_map.get(curr_row, curr_col) {
-				if height > 0 {
-					let neighbor_coords = Direction::values().into_iter()
-						.flat_map(|dir| dir.offset_from((curr_row, curr_col)));
-					let filtered_neighbors = neighbor_coords.filter(|&(nbr_row, nbr_col)| {
-						topographical_map.get(nbr_row, nbr_col).is_some_and(|&nbr_height| nbr_height == height - 1)
-					});
-					nodes_to_process.extend(filtered_neighbors);
-				}
-			}
-		}
-
-		can_reach_this_peak.iter().for_each(|&(row_to_update, col_to_update)| {
-			if let Some(curr_score) = score_map.get_mut(row_to_update, col_to_update) {
-				*curr_score += 1;
-			}
-		})
-	});
-
-	let score_sum = topographical_map.indexed_iter().filter_map(|(coords, &height)| {
-		if height == 0 { Some(coords) } else { None }
-	}).flat_map(|(row, col)| score_map.get(row, col)).sum();
-
-	Ok(score_sum)
+pub type Peaks = AHashSet<(isize, isize)>;
+
+// every trailhead, along with every peak reachable from it
+pub fn trailhead_peaks(input: &str) -> Result<Vec<((usize, usize), Peaks)>> {
+	let topographical_map = parse(input)?;
+	let (mut peak_map, _) = peaks_and_ratings(&topographical_map);
+
+	let trailheads = topographical_map.indexed_iter().filter(|&(_, &height)| height == 0)
+		.map(|(coords, _)| (coords, std::mem::take(&mut peak_map[coords])))
+		.collect();
+	Ok(trailheads)
 }
 
 pub fn part2(input: &str) -> Result<u64> {
-	let topographical_map: Grid<_> = parse(input).into();
-
-	let mut rating_map: Grid<Option<u64>> = {
-		let (grid_rows, grid_cols) = topographical_map.size();
-		Grid::init(grid_rows, grid_cols, None)
-	};
-
-	let calculate_rating = |(row, col): (usize, usize)| -> Option<u64> {
-		fn rec((row, col): (isize, isize), topographical_map: &Grid<u64>, rating_map: &mut Grid<Option<u64>>) -> Option<u64> {
-			if let Some(rating) = rating_map.get(row, col).copied().flatten() {
-				return Some(rating);
-			}
-
-			let calculated_rating = if let Some(&height) = topographical_map.get(row, col) {
-				if height == 9 {
-					Some(1)
-				} else {
-					let neighbor_coords = Direction::values().into_iter()
-						.flat_map(|dir| dir.offset_from((row, col)));
-					let filtered_neighbors = neighbor_coords.filter(|&(nbr_row, nbr_col)| {
-						topographical_map.get(nbr_row, nbr_col).is_some_and(|&nbr_height| nbr_height == height + 1)
-					});
+	let topographical_map = parse(input)?;
+	let (_, rating_map) = peaks_and_ratings(&topographical_map);
+
+	Ok(topographical_map.indexed_iter().filter(|&(_, &height)| height == 0).map(|(coords, _)| rating_map[coords]).sum())
+}
 
-					let rating = filtered_neighbors.flat_map(|nbr_coords| rec(nbr_coords, topographical_map, rating_map)).sum();
-					Some(rating)
-				}
+// trailhead scores and ratings summed, from one pass over the map
+pub fn scores_and_ratings(input: &str) -> Result<(u64, u64)> {
+	let topographical_map = parse(input)?;
+	let (peak_map, rating_map) = peaks_and_ratings(&topographical_map);
+
+	let totals = topographical_map.indexed_iter().filter(|&(_, &height)| height == 0)
+		.fold((0, 0), |(score, rating), (coords, _)| {
+			(score + peak_map[coords].len() as u64, rating + rating_map[coords])
+		});
+	Ok(totals)
+}
+
+// works down from the peaks, where each cell collects the peaks and the number of trails
+// reachable from its uphill neighbors
+fn peaks_and_ratings(topographical_map: &Grid<u64>) -> (Grid<Peaks>, Grid<u64>) {
+	let (grid_rows, grid_cols) = topographical_map.size();
+
+	let mut peak_map: Grid<Peaks> = Grid::init(grid_rows, grid_cols, AHashSet::new());
+	let mut rating_map: Grid<u64> = Grid::init(grid_rows, grid_cols, 0);
+	for height in (0..=9).rev() {
+		let cells = topographical_map.indexed_iter()
+			.filter(|&(_, &cell_height)| cell_height == height)
+			.map(|((row, col), _)| (row as isize, col as isize))
+			.collect::<Vec<_>>();
+
+		cells.into_iter().for_each(|(row, col)| {
+			let (peaks, rating) = if height == 9 {
+				(AHashSet::from([(row, col)]), 1)
 			} else {
-				None
+				let neighbor_coords = Direction::values().into_iter()
+					.flat_map(|dir| dir.offset_from((row, col)))
+					.filter(|&(nbr_row, nbr_col)| {
+						topographical_map.get_signed(nbr_row, nbr_col).is_some_and(|&nbr_height| nbr_height == height + 1)
+					});
+				neighbor_coords.fold((AHashSet::new(), 0), |(mut peaks, rating), (nbr_row, nbr_col)| {
+					peaks.extend(peak_map.get_signed(nbr_row, nbr_col).unwrap());
+					(peaks, rating + rating_map.get_signed(nbr_row, nbr_col).unwrap())
+				})
 			};
+			*peak_map.get_signed_mut(row, col).unwrap() = peaks;
+			*rating_map.get_signed_mut(row, col).unwrap() = rating;
+		});
+	}
 
-			if calculated_rating.is_some() {
-				*rating_map.get_mut(row, col).unwrap() = calculated_rating;
-			}
-			calculated_rating
-		}
-		rec((row.try_into().ok()?, col.try_into().ok()?), &topographical_map, &mut rating_map)
-	};
-
-	let rating_sum = topographical_map.indexed_iter().filter_map(|(coords, &height)| {
-		if height == 0 { Some(coords) } else { None }
-	}).flat_map(calculate_rating).sum();
-	Ok(rating_sum)
+	(peak_map, rating_map)
 }
 
 #[cfg(test)]
@@ -163,9 +118,47 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_trailhead_peaks() -> Result<()> {
+		let trailheads = trailhead_peaks(TEST)?;
+		assert_eq!(9, trailheads.len());
+		assert_eq!(36, trailheads.iter().map(|(_, peaks)| peaks.len()).sum::<usize>());
+		// the first trailhead in the top row reaches 5 peaks
+		assert_eq!(((0, 2), 5), trailheads.first().map(|(coords, peaks)| (*coords, peaks.len())).unwrap());
+		Ok(())
+	}
+
 	#[test]
 	fn test_part_two() -> Result<()> {
 		assert_eq!(81, part2(TEST)?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_score_grid() -> Result<()> {
+		let score_map = score_grid(TEST)?;
+		assert_eq!((8, 8), score_map.size());
+		// trailheads from the puzzle description
+		assert_eq!(5, score_map[(0, 2)]);
+		assert_eq!(6, score_map[(0, 4)]);
+		assert_eq!(3, score_map[(5, 5)]);
+		// every peak reaches only itself
+		assert_eq!(1, score_map[(0, 1)]);
+		Ok(())
+	}
+
+	#[test]
+	fn test_scores_and_ratings() -> Result<()> {
+		assert_eq!((36, 81), scores_and_ratings(TEST)?);
+		assert_eq!((part1(TEST)?, part2(TEST)?), scores_and_ratings(TEST)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_trail_along_edge() -> Result<()> {
+		// the trail hugs the top and left edges, so every step also looks past them
+		assert_eq!(1, part1("0123\n9994\n8765")?);
+		assert_eq!(1, part2("0123\n9994\n8765")?);
+		Ok(())
+	}
 }
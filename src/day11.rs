@@ -1,61 +1,85 @@
 use ahash::AHashMap;
 use anyhow::*;
-use nom::character::complete::{char, digit1};
-use nom::combinator::{all_consuming, map_res};
+use nom::character::complete::{char, digit1, line_ending};
+use nom::combinator::{all_consuming, map_res, opt};
 use nom::{Finish, IResult};
 use nom::multi::separated_list1;
+use nom::sequence::terminated;
+use crate::memo::Memoizer;
 
 fn parse_u64(input: &str) -> IResult<&str, u64> {
 	map_res(digit1, |num: &str| num.parse())(input)
 }
 
 fn parse(input: &str) -> Vec<u64> {
-	let (_, result) = all_consuming(separated_list1(char(' '), parse_u64))(input).finish().unwrap();
+	let (_, result) = all_consuming(terminated(separated_list1(char(' '), parse_u64), opt(line_ending)))(input).finish().unwrap();
 	result
 }
 
+pub struct Day11;
+
 pub fn part1(input: &str) -> Result<u64> {
+	count_stones(input, 25)
+}
+
+pub fn count_stones(input: &str, blinks: u64) -> Result<u64> {
 	let rocks = parse(input);
 
-	Ok(count_rocks(rocks, 25))
+	Ok(count_rocks(rocks, blinks))
+}
+
+// what a single rock turns into after one blink, either one rock or two
+fn blink_rock(rock: u64) -> (u64, Option<u64>) {
+	if rock == 0 {
+		(1, None)
+	} else if (rock.ilog10() + 1) % 2 == 0 {
+		let num_digits = rock.ilog10() + 1;
+		let base = 10u64.pow(num_digits / 2);
+		(rock / base, Some(rock % base))
+	} else {
+		(rock * 2024, None)
+	}
 }
 
 fn count_rocks(rocks: Vec<u64>, max_age: u64) -> u64 {
-	let mut rock_age_count_cache = AHashMap::new();
-	let mut map_rocks = |rock: u64, depth: u64| -> u64 {
-		fn rec(rock: u64, depth: u64, rock_age_count_cache: &mut AHashMap<(u64, u64), u64>) -> u64 {
-			if let Some(&count) = rock_age_count_cache.get(&(rock, depth)) {
-				return count;
+	// how many rocks a single rock turns into after some number of blinks
+	let mut rock_count = Memoizer::new(|rock_count, (rock, depth): (u64, u64)| {
+		if depth == 0 {
+			1
+		} else {
+			match blink_rock(rock) {
+				(left, Some(right)) => rock_count.get((left, depth - 1)) + rock_count.get((right, depth - 1)),
+				(rock, None) => rock_count.get((rock, depth - 1)),
 			}
-
-			let count = if depth == 0 {
-				1
-			} else {
-				if rock == 0 {
-					rec(1, depth - 1, rock_age_count_cache)
-				} else if (rock.ilog10() + 1) % 2 == 0 {
-					let num_digits = rock.ilog10() + 1;
-					let base = 10u64.pow(num_digits / 2);
-					rec(rock / base, depth - 1, rock_age_count_cache)
-						+ rec(rock % base, depth - 1, rock_age_count_cache)
-				} else {
-					rec(rock * 2024, depth - 1, rock_age_count_cache)
-				}
-			};
-			rock_age_count_cache.insert((rock, depth), count);
-
-			count
 		}
-		rec(rock, depth, &mut rock_age_count_cache)
-	};
+	});
 
-	rocks.into_iter().map(|rock| map_rocks(rock, max_age)).sum()
+	rocks.into_iter().map(|rock| rock_count.get((rock, max_age))).sum()
 }
 
-pub fn part2(input: &str) -> Result<u64> {
+// how many stones carry each number after the given number of blinks
+pub fn stone_histogram(input: &str, blinks: u64) -> Result<AHashMap<u64, u64>> {
 	let rocks = parse(input);
 
-	Ok(count_rocks(rocks, 75))
+	let mut histogram = AHashMap::new();
+	rocks.into_iter().for_each(|rock| *histogram.entry(rock).or_default() += 1);
+	for _ in 0..blinks {
+		let mut next_histogram = AHashMap::with_capacity(histogram.len());
+		histogram.into_iter().for_each(|(rock, count)| {
+			let (left, right) = blink_rock(rock);
+			*next_histogram.entry(left).or_default() += count;
+			if let Some(right) = right {
+				*next_histogram.entry(right).or_default() += count;
+			}
+		});
+		histogram = next_histogram;
+	}
+
+	Ok(histogram)
+}
+
+pub fn part2(input: &str) -> Result<u64> {
+	count_stones(input, 75)
 }
 
 #[cfg(test)]
@@ -70,6 +94,13 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_part_two() -> Result<()> {
+		assert_eq!(65601038650482, part2(TEST)?);
+		assert_eq!(part2(TEST)?, count_stones(TEST, 75)?);
+		Ok(())
+	}
+
 	#[test]
 	fn test_one_blink() -> Result<()> {
 		assert_eq!(3, count_rocks(parse(TEST), 1));
@@ -106,4 +137,20 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_stone_histogram() -> Result<()> {
+		let histogram = stone_histogram(TEST, 6)?;
+		assert_eq!(count_stones(TEST, 6)?, histogram.values().sum());
+		// 2097446912 14168 4048 2 0 2 4 40 48 2024 40 48 80 96 2 8 6 7 6 0 3 2
+		assert_eq!(Some(&4), histogram.get(&2));
+		assert_eq!(Some(&2), histogram.get(&40));
+		Ok(())
+	}
+
+	#[test]
+	fn test_crlf_line_ending() -> Result<()> {
+		assert_eq!(55312, part1("125 17\r\n")?);
+		assert_eq!(55312, part1("125 17\n")?);
+		Ok(())
+	}
 }
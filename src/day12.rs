@@ -1,6 +1,6 @@
 use std::cell::Cell;
 use std::collections::BTreeSet;
-use ahash::AHashSet;
+use ahash::{AHashMap, AHashSet};
 use anyhow::*;
 use grid::Grid;
 use itertools::Itertools;
@@ -10,40 +10,7 @@ use nom::combinator::{all_consuming, map, opt};
 use nom::{Finish, IResult};
 use nom::multi::many1;
 use nom::sequence::terminated;
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-enum Direction {
-	North,
-	East,
-	South,
-	West,
-}
-impl Direction {
-	pub const fn get_offset(&self) -> (isize, isize) {
-		match self {
-			Direction::North => (-1, 0),
-			Direction::East => (0, 1),
-			Direction::South => (1, 0),
-			Direction::West => (0, -1),
-		}
-	}
-
-	pub fn offset_from(&self, (row, col): (impl TryInto<isize>, impl TryInto<isize>)) -> Option<(isize, isize)> {
-		let row_isize = row.try_into().ok()?;
-		let col_isize = col.try_into().ok()?;
-		let (row_offset, col_offset) = self.get_offset();
-		Some((row_isize + row_offset, col_isize + col_offset))
-	}
-
-	pub const fn values() -> [Direction; 4] {
-		[
-			Direction::North,
-			Direction::East,
-			Direction::South,
-			Direction::West
-		]
-	}
-}
+use crate::direction::Direction;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 struct MapCell {
@@ -71,21 +38,37 @@ fn parse(input: &str) -> Vec<Vec<MapCell>> {
 	result
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-struct RegionInfo {
-	id: usize,
-	area: u64,
-	perimeter: u64
+pub struct Day12;
+
+pub fn part1(input: &str) -> Result<u64> {
+	Ok(regions(input)?.into_iter().map(|(_, area, perimeter, _)| area * perimeter).sum())
 }
-impl RegionInfo {
-	pub fn with_size(id: usize, area: u64, perimeter: u64) -> Self {
-		Self { id, area, perimeter }
-	}
+
+// with diagonal connectivity, plants touching only at a corner still share a region, but fences are still orthogonal
+pub fn part1_with_connectivity(input: &str, diagonal: bool) -> Result<u64> {
+	let grid: Grid<_> = parse(input).into();
+
+	Ok(flood_regions(&grid, diagonal).into_iter()
+		.map(|(_, region_cells)| region_cells.len() as u64 * region_perimeter(&region_cells))
+		.sum())
 }
 
-pub fn part1(input: &str) -> Result<u64> {
+pub fn part2(input: &str) -> Result<u64> {
+	Ok(regions(input)?.into_iter().map(|(_, area, _, sides)| area * sides).sum())
+}
+
+// plant, area, perimeter and number of sides of every region
+pub fn regions(input: &str) -> Result<Vec<(char, u64, u64, u64)>> {
 	let grid: Grid<_> = parse(input).into();
 
+	Ok(flood_regions(&grid, false).into_iter().map(|(plant, region_cells)| {
+		(plant, region_cells.len() as u64, region_perimeter(&region_cells), region_sides(&region_cells))
+	}).collect())
+}
+
+fn flood_regions(grid: &Grid<MapCell>, diagonal: bool) -> Vec<(char, AHashSet<(isize, isize)>)> {
+	const DIAGONAL_OFFSETS: [(isize, isize); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+
 	let mut regions = Vec::new();
 	grid.indexed_iter().for_each(|((grid_row, grid_col), grid_cell)| {
 		let (grid_row, grid_col) = (grid_row.try_into().unwrap(), grid_col.try_into().unwrap());
@@ -107,135 +90,61 @@ pub fn part1(input: &str) -> Result<u64> {
 						// add neighboring cells to queue
 						region_cells_queue.extend(Direction::values().into_iter()
 							.flat_map(|dir| dir.offset_from((row, col))));
+						if diagonal {
+							region_cells_queue.extend(DIAGONAL_OFFSETS.into_iter()
+								.map(|(row_offset, col_offset)| (row + row_offset, col + col_offset)));
+						}
 					}
 				}
 			}
 
-			let region_area = region_cells.len();
-			let region_perimeter = region_cells.iter().map(|&(region_row, region_col)|
-				4 - Direction::values().into_iter()
-					.flat_map(|dir| dir.offset_from((region_row, region_col)))
-					.filter_map(|(nbr_row, nbr_col)| region_cells.get(&(nbr_row, nbr_col)))
-					.count() as u64
-			).sum();
-
-			regions.push(RegionInfo::with_size(region_id, region_area as u64, region_perimeter));
+			regions.push((region_plant, region_cells));
 		}
 	});
 
-	Ok(regions.into_iter().map(|region| region.area * region.perimeter).sum())
-}
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-struct RegionInfoPartTwo {
-	id: usize,
-	area: u64,
-	sides: u64
-}
-impl RegionInfoPartTwo {
-	pub fn with_size(id: usize, area: u64, sides: u64) -> Self {
-		Self { id, area, sides }
-	}
+	regions
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Edge {
-	Horizontal{ col: isize, inside_row: isize, outside_row: isize },
-	Vertical{ row: isize, inside_col: isize, outside_col: isize },
+fn region_perimeter(region_cells: &AHashSet<(isize, isize)>) -> u64 {
+	region_cells.iter().map(|&(region_row, region_col)|
+		4 - Direction::values().into_iter()
+			.flat_map(|dir| dir.offset_from((region_row, region_col)))
+			.filter_map(|(nbr_row, nbr_col)| region_cells.get(&(nbr_row, nbr_col)))
+			.count() as u64
+	).sum()
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum ContinuousEdge {
-	Horizontal{ start_col: isize, end_col: isize, inside_row: isize, outside_row: isize },
-	Vertical{ start_row: isize, end_row: isize, inside_col: isize, outside_col: isize }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum EdgeOrientation {
+	Horizontal,
+	Vertical,
 }
 
-pub fn part2(input: &str) -> Result<u64> {
-	let grid: Grid<_> = parse(input).into();
-
-	let mut regions = Vec::new();
-	grid.indexed_iter().for_each(|((grid_row, grid_col), grid_cell)| {
-		let (grid_row, grid_col) = (grid_row.try_into().unwrap(), grid_col.try_into().unwrap());
-		if grid_cell.region.get().is_none() {
-
-			let region_id = regions.len();
-			let region_plant = grid_cell.plant;
-			let mut region_cells = AHashSet::from([(grid_row, grid_col)]);
-			let mut region_cells_queue = BTreeSet::from([(grid_row, grid_col)]);
-
-			while let Some((row, col)) = region_cells_queue.pop_first() {
-				// does this cell exist? is it already assigned a region? is it the right plant?
-				if let Some(next_cell) = grid.get(row, col) {
-					if next_cell.region.get().is_none() && next_cell.plant == region_plant {
-						// add this cell to the current region
-						next_cell.region.set(Some(region_id));
-						region_cells.insert((row, col));
-
-						// add neighboring cells to queue
-						region_cells_queue.extend(Direction::values().into_iter()
-							.flat_map(|dir| dir.offset_from((row, col))));
-					}
-				}
-			}
-
-			let region_area = region_cells.len();
-			let edges = region_cells.iter().sorted_unstable().flat_map(|&(region_row, region_col)| {
-				// for this cell, find neighboring cells that are not part of this region
-				Direction::values().into_iter()
-					.flat_map(move |dir| dir.offset_from((region_row, region_col)))
-					.filter(|&(nbr_row, nbr_col)| region_cells.get(&(nbr_row, nbr_col)).is_none())
-					// and find the edges
-					.map(move |(nbr_row, nbr_col)| {
-						if nbr_row == region_row {
-							Edge::Vertical { row: region_row, inside_col: region_col, outside_col: nbr_col }
-						} else {
-							Edge::Horizontal { col: region_col, inside_row: region_row, outside_row: nbr_row }
-						}
-					})
-			}).collect_vec();
-			let distinct_edges = edges.into_iter().fold(Vec::new(), |mut edge_runs, next_edge| {
-				if let Some(matching_edge_ix) = edge_runs.iter().position(|&edge_run| {
-					// are these the same kind of edge? are they adjacent?
-					match (edge_run, next_edge) {
-						(ContinuousEdge::Horizontal { start_col, end_col, inside_row: run_inside_row, outside_row: run_outside_row },
-							Edge::Horizontal { col: edge_col, inside_row: edge_inside_row, outside_row: edge_outside_row }) => {
-							edge_inside_row == run_inside_row && edge_outside_row == run_outside_row && (edge_col == start_col - 1 || edge_col == end_col + 1)
-						},
-						(ContinuousEdge::Vertical { start_row, end_row, inside_col: run_inside_col, outside_col: run_outside_col },
-							Edge::Vertical { row: edge_row, inside_col: edge_inside_col, outside_col: edge_outside_col }) => {
-							edge_inside_col == run_inside_col && edge_outside_col == run_outside_col && (edge_row == start_row - 1 || edge_row == end_row + 1)
-						},
-						_ => false,
-					}
-				}) {
-					match (edge_runs.get_mut(matching_edge_ix).unwrap(), next_edge) {
-						(ContinuousEdge::Horizontal { start_col, end_col, .. }, Edge::Horizontal { col: edge_col, .. }) => {
-							*start_col = edge_col.min(*start_col);
-							*end_col = edge_col.max(*end_col);
-						},
-						(ContinuousEdge::Vertical { start_row, end_row, .. }, Edge::Vertical { row: edge_row, .. }) => {
-							*start_row = edge_row.min(*start_row);
-							*end_row = edge_row.max(*end_row);
-						},
-						_ => {panic!("found matching edge but second pattern match failed!");}
-					};
+fn region_sides(region_cells: &AHashSet<(isize, isize)>) -> u64 {
+	// edges can only join up into a side with edges of the same orientation, between the same inside and outside row/col,
+	// so group them by that and only keep track of where along the row/col each edge is
+	let mut edge_lines: AHashMap<(EdgeOrientation, isize, isize), Vec<isize>> = AHashMap::new();
+	region_cells.iter().for_each(|&(region_row, region_col)| {
+		// for this cell, find neighboring cells that are not part of this region
+		Direction::values().into_iter()
+			.flat_map(|dir| dir.offset_from((region_row, region_col)))
+			.filter(|nbr| !region_cells.contains(nbr))
+			// and find the edges
+			.for_each(|(nbr_row, nbr_col)| {
+				let (line, pos) = if nbr_row == region_row {
+					((EdgeOrientation::Vertical, region_col, nbr_col), region_row)
 				} else {
-					let new_edge_run = match next_edge {
-						Edge::Horizontal { col, inside_row, outside_row } =>
-							ContinuousEdge::Horizontal { start_col: col, end_col: col, inside_row, outside_row },
-						Edge::Vertical { row, inside_col, outside_col } =>
-							ContinuousEdge::Vertical { start_row: row, end_row: row, inside_col, outside_col }
-					};
-					edge_runs.push(new_edge_run);
-				}
-				edge_runs
-			}).len();
-
-			regions.push(RegionInfoPartTwo::with_size(region_id, region_area as u64, distinct_edges as u64));
-		}
+					((EdgeOrientation::Horizontal, region_row, nbr_row), region_col)
+				};
+				edge_lines.entry(line).or_default().push(pos);
+			});
 	});
 
-	Ok(regions.into_iter().map(|region| region.area * region.sides).sum())
+	// every gap along a line starts a new side
+	edge_lines.into_values().map(|mut positions| {
+		positions.sort_unstable();
+		1 + positions.into_iter().tuple_windows().filter(|&(prev_pos, next_pos)| next_pos != prev_pos + 1).count() as u64
+	}).sum()
 }
 
 #[cfg(test)]
@@ -282,6 +191,29 @@ MMMISSJEEE";
 		Ok(())
 	}
 
+	#[test]
+	fn test_regions_large() -> Result<()> {
+		let regions = regions(LARGE_TEST)?;
+		assert_eq!(11, regions.len());
+		// the R region in the top left corner is found first
+		assert_eq!(('R', 12, 18, 10), regions[0]);
+		assert_eq!(2, regions.iter().filter(|&&(plant, ..)| plant == 'C').count());
+		Ok(())
+	}
+
+	#[test]
+	fn test_part_one_diagonal() -> Result<()> {
+		const X_SHAPE: &str = "ABA
+BAB
+ABA";
+		// orthogonally every cell is its own region
+		assert_eq!(9 * 4, part1_with_connectivity(X_SHAPE, false)?);
+		assert_eq!(part1(X_SHAPE)?, part1_with_connectivity(X_SHAPE, false)?);
+		// diagonally the A's make one region of 5 and the B's one region of 4
+		assert_eq!((5 * 20) + (4 * 16), part1_with_connectivity(X_SHAPE, true)?);
+		Ok(())
+	}
+
 
 
 	#[test]
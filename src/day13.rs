@@ -1,4 +1,4 @@
-use std::ops::{Add, ControlFlow, Div, Mul, Rem, Sub};
+use std::ops::{Add, Div, Mul, Rem, Sub};
 use anyhow::*;
 use nom::bytes::complete::tag;
 use nom::character::complete::{digit1, line_ending, one_of};
@@ -9,9 +9,9 @@ use nom::sequence::{preceded, separated_pair, terminated, tuple};
 use num::Integer;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-struct ButtonDelta {
-	x: u64,
-	y: u64,
+pub struct ButtonDelta {
+	pub x: u64,
+	pub y: u64,
 }
 impl Mul<u64> for ButtonDelta {
 	type Output = ButtonDelta;
@@ -55,9 +55,9 @@ impl Mul<&u64> for &ButtonDelta {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-struct PrizeLocation {
-	x: u64,
-	y: u64,
+pub struct PrizeLocation {
+	pub x: u64,
+	pub y: u64,
 }
 impl Add<u64> for PrizeLocation {
 	type Output = PrizeLocation;
@@ -93,20 +93,22 @@ impl Sub<ButtonDelta> for PrizeLocation {
 		}
 	}
 }
-impl PrizeLocation {
-	pub fn checked_sub(self, rhs: ButtonDelta) -> Option<PrizeLocation> {
-		Some(PrizeLocation {
-			x: self.x.checked_sub(rhs.x)?,
-			y: self.y.checked_sub(rhs.y)?
-		})
-	}
-}
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-struct ClawMachine {
-	a: ButtonDelta,
-	b: ButtonDelta,
-	prize: PrizeLocation
+pub struct ClawMachine {
+	pub a: ButtonDelta,
+	pub b: ButtonDelta,
+	pub prize: PrizeLocation
+}
+impl Add<u64> for ClawMachine {
+	type Output = ClawMachine;
+
+	fn add(self, rhs: u64) -> Self::Output {
+		Self::Output {
+			prize: self.prize + rhs,
+			..self
+		}
+	}
 }
 
 fn parse_u64(input: &str) -> IResult<&str, u64> {
@@ -137,93 +139,143 @@ fn parse_claw_machine(input: &str) -> IResult<&str, ClawMachine> {
 	)), |(a, b, prize)| ClawMachine { a, b, prize })(input)
 }
 
-fn parse(input: &str) -> Vec<ClawMachine> {
-	let (_, result) = all_consuming(separated_list1(line_ending, parse_claw_machine))(input).finish().unwrap();
-	result
+// adding an offset to a machine moves its prize that far along both axes, so the part 2 machines are
+// `parse_machines(input)?.into_iter().map(|claw_machine| claw_machine + 10000000000000)`
+pub fn parse_machines(input: &str) -> Result<Vec<ClawMachine>> {
+	let (_, result) = all_consuming(separated_list1(line_ending, parse_claw_machine))(input).finish()
+		.map_err(|err| anyhow!("could not parse input at {:?}: {:?}", err.input.lines().next().unwrap_or_default(), err.code))?;
+	Ok(result)
 }
 
+const A_COST: u64 = 3;
+const B_COST: u64 = 1;
+
+pub struct Day13;
+
 pub fn part1(input: &str) -> Result<u64> {
-	let claw_machines = parse(input);
+	let claw_machines = parse_machines(input)?;
 
-	const A_COST: u64 = 3;
-	const B_COST: u64 = 1;
 	const MAX_PRESSES: u64 = 100;
 
 	let spent_tokens = claw_machines.into_iter().filter_map(|claw_machine| {
-		if let ControlFlow::Break(Some(token_cost)) = (0..=MAX_PRESSES).try_for_each(|a_presses| {
-			let a_distance = claw_machine.a * a_presses;
-			if let Some(remaining_distance) = claw_machine.prize.checked_sub(a_distance) {
-				if let Some(b_presses) = match (remaining_distance % claw_machine.b, remaining_distance / claw_machine.b) {
-					((x_rem, y_rem), (x_mult, y_mult))
-					if x_rem == 0 && y_rem == 0 && x_mult == y_mult && x_mult <= MAX_PRESSES => {
-						Some(x_mult)
-					},
-					_ => None
-				} {
-					ControlFlow::Break(Some((a_presses * A_COST) + (b_presses * B_COST)))
-				} else {
-					ControlFlow::Continue(())
-				}
-			} else {
-				ControlFlow::Break(None)
-			}
-		}) {
-			Some(token_cost)
-		} else {
-			None
-		}
+		let (a_presses, b_presses) = solve_presses(&claw_machine, MAX_PRESSES)?;
+		Some((a_presses * A_COST) + (b_presses * B_COST))
 	}).sum();
 
 	Ok(spent_tokens)
 }
 
 pub fn part2(input: &str) -> Result<u64> {
-	let claw_machines = parse(input);
-
-	const A_COST: i64 = 3;
-	const B_COST: i64 = 1;
-
-	let all_min_costs = claw_machines.into_iter().map(|claw_machine| ClawMachine {
-		prize: claw_machine.prize + 10000000000000,
-		..claw_machine
-	}).filter_map(|claw_machine| {
-		let ClawMachine {
-			a: ButtonDelta { x: ax, y: ay },
-			b: ButtonDelta { x: bx, y: by },
-			prize: PrizeLocation { x: px, y: py }
-		} = claw_machine;
-		let (ax, ay, bx, by, px, py) = (ax as i64, ay as i64, bx as i64, by as i64, px as i64, py as i64);
-
-		let x_gcd = ax.gcd(&bx);
-		let y_gcd = ay.gcd(&by);
-
-		if px as u64 % x_gcd as u64 != 0 || py as u64 % y_gcd as u64 != 0  {
-			return None;
-		}
+	let all_min_costs = solve_machines(input, 10000000000000)?.into_iter().flatten()
+		.map(|(a_presses, b_presses)| (a_presses * A_COST) + (b_presses * B_COST))
+		.sum();
 
-		let a_dividend = (by * px) - (bx * py);
-		let a_divisor = (ax * by) - (ay * bx);
-		let a_rem = a_dividend % a_divisor;
-		if a_rem != 0 {
-			return None;
-		}
+	Ok(all_min_costs)
+}
 
-		let a_presses = ((by * px) - (bx * py)) / ((ax * by) - (ay * bx));
-		let b_dividend = px - (ax * a_presses);
-		let b_rem = b_dividend % bx;
-		if b_rem != 0 {
-			return None;
-		}
+pub fn solve_machines(input: &str, prize_offset: u64) -> Result<Vec<Option<(u64, u64)>>> {
+	let claw_machines = parse_machines(input)?;
+
+	Ok(claw_machines.into_iter().map(|claw_machine| solve_presses(&(claw_machine + prize_offset), u64::MAX)).collect())
+}
+
+// cheapest presses of each button that reach the prize, pressing neither more than max_presses times
+//
+// unless the buttons are parallel there's at most one way to reach the prize, solve for it with
+// cramer's rule and reject anything that isn't a whole, non-negative press count
+fn solve_presses(claw_machine: &ClawMachine, max_presses: u64) -> Option<(u64, u64)> {
+	let ClawMachine {
+		a: ButtonDelta { x: ax, y: ay },
+		b: ButtonDelta { x: bx, y: by },
+		prize: PrizeLocation { x: px, y: py }
+	} = *claw_machine;
+	// with the part 2 offset the prize is ~10^13, so products with button deltas can overflow i64
+	let (ax, ay, bx, by, px, py) = (ax as i128, ay as i128, bx as i128, by as i128, px as i128, py as i128);
+
+	// buttons that don't move along an axis can only reach a prize at zero on it
+	let unreachable = |gcd: i128, p: i128| if gcd == 0 { p != 0 } else { p % gcd != 0 };
+	if unreachable(ax.gcd(&bx), px) || unreachable(ay.gcd(&by), py) {
+		return None;
+	}
+
+	let divisor = (ax * by) - (ay * bx);
+	if divisor == 0 {
+		return solve_parallel_presses((ax, ay), (bx, by), (px, py), max_presses as i128);
+	}
+	let a_dividend = (by * px) - (bx * py);
+	let b_dividend = (ax * py) - (ay * px);
+	if a_dividend % divisor != 0 || b_dividend % divisor != 0 {
+		return None;
+	}
+
+	// negative press counts fail the conversion too
+	let a_presses = u64::try_from(a_dividend / divisor).ok()?;
+	let b_presses = u64::try_from(b_dividend / divisor).ok()?;
+	(a_presses <= max_presses && b_presses <= max_presses).then_some((a_presses, b_presses))
+}
+
+// both buttons move along the same line, so there can be many ways to split the presses between them
+fn solve_parallel_presses((ax, ay): (i128, i128), (bx, by): (i128, i128), (px, py): (i128, i128), max_presses: i128) -> Option<(u64, u64)> {
+	// the prize has to be on that line too
+	if ax * py != ay * px || bx * py != by * px {
+		return None;
+	}
+	// on the line one axis decides the other, use one the buttons actually move along
+	let (a_step, b_step, prize) = if ax != 0 || bx != 0 { (ax, bx, px) } else { (ay, by, py) };
+
+	let (a_presses, b_presses) = match (a_step, b_step) {
+		// neither button moves, the prize must already be under the claw
+		(0, 0) => (0, 0),
+		// a button that doesn't move is never worth pressing
+		(0, _) => (0, (prize % b_step == 0).then_some(prize / b_step)?),
+		(_, 0) => ((prize % a_step == 0).then_some(prize / a_step)?, 0),
+		_ => {
+			if prize % a_step.gcd(&b_step) != 0 {
+				return None;
+			}
+			// every split is the fewest A presses that leave a multiple of B, traded off in whole
+			// groups where a_cycle presses of A replace b_cycle presses of B
+			let lcm = a_step.lcm(&b_step);
+			let (a_cycle, b_cycle) = (lcm / a_step, lcm / b_step);
+			let min_a = (0..a_cycle).find(|&a_presses| (prize - (a_presses * a_step)) % b_step == 0)?;
+			let max_b = (prize - (min_a * a_step)) / b_step;
+			if max_b < 0 {
+				return None;
+			}
+
+			// keep both press counts within the limit
+			let min_trades = Integer::div_ceil(&(max_b - max_presses).max(0), &b_cycle);
+			let max_trades = ((max_presses - min_a) / a_cycle).min(max_b / b_cycle);
+			if min_a > max_presses || min_trades > max_trades {
+				return None;
+			}
 
-		let b_presses = b_dividend / bx;
-		if a_presses < 0 || b_presses < 0 {
-			None
-		} else {
-			Some(((a_presses * A_COST) + (b_presses * B_COST)) as u64)
+			// the cost changes by the same amount every trade, so the cheapest is at one end
+			let trades = if (a_cycle as u64 * A_COST) < (b_cycle as u64 * B_COST) { max_trades } else { min_trades };
+			(min_a + (trades * a_cycle), max_b - (trades * b_cycle))
 		}
-	}).sum();
+	};
+	if a_presses > max_presses || b_presses > max_presses {
+		return None;
+	}
+	Some((a_presses as u64, b_presses as u64))
+}
 
-	Ok(all_min_costs)
+pub fn real_solution(claw_machine: &ClawMachine, prize_offset: u64) -> (f64, f64) {
+	// solve the linear system without requiring whole button presses,
+	// useful to see how far off an unsolvable machine is from having an integer solution
+	let ClawMachine {
+		a: ButtonDelta { x: ax, y: ay },
+		b: ButtonDelta { x: bx, y: by },
+		prize: PrizeLocation { x: px, y: py }
+	} = *claw_machine + prize_offset;
+	let (ax, ay, bx, by, px, py) = (ax as f64, ay as f64, bx as f64, by as f64, px as f64, py as f64);
+
+	let determinant = (ax * by) - (ay * bx);
+	let a_presses = ((by * px) - (bx * py)) / determinant;
+	let b_presses = ((ax * py) - (ay * px)) / determinant;
+
+	(a_presses, b_presses)
 }
 
 #[cfg(test)]
@@ -251,4 +303,112 @@ Prize: X=18641, Y=10279";
 		assert_eq!(480, part1(TEST)?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_parse_machines() -> Result<()> {
+		let claw_machines = parse_machines(TEST)?;
+		assert_eq!(4, claw_machines.len());
+		assert_eq!(ButtonDelta { x: 94, y: 34 }, claw_machines[0].a);
+		assert_eq!((94, 34), (claw_machines[0].a.x, claw_machines[0].a.y));
+
+		let offset_machine = claw_machines[0] + 10000000000000;
+		assert_eq!(PrizeLocation { x: 10000000008400, y: 10000000005400 }, offset_machine.prize);
+		assert_eq!(claw_machines[0].b, offset_machine.b);
+
+		assert!(parse_machines("Button A: X+94, Y+34\nButton B: X+22").is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_solve_machines() -> Result<()> {
+		assert_eq!(vec![Some((80, 40)), None, Some((38, 86)), None], solve_machines(TEST, 0)?);
+
+		// with the offset only the second and fourth machines can be won
+		let offset_presses = solve_machines(TEST, 10000000000000)?;
+		assert_eq!(vec![false, true, false, true], offset_presses.iter().map(Option::is_some).collect::<Vec<_>>());
+		Ok(())
+	}
+
+	#[test]
+	fn test_part_one_matches_brute_force() -> Result<()> {
+		// cheapest of every way to reach the prize, favoring fewer A presses on a tie
+		fn brute_force_presses(claw_machine: &ClawMachine) -> Option<(u64, u64)> {
+			(0..=100).filter_map(|a_presses| {
+				let a_distance = claw_machine.a * a_presses;
+				if a_distance.x > claw_machine.prize.x || a_distance.y > claw_machine.prize.y {
+					return None;
+				}
+				let remaining_distance = claw_machine.prize - a_distance;
+				match (remaining_distance % claw_machine.b, remaining_distance / claw_machine.b) {
+					((0, 0), (x_mult, y_mult)) if x_mult == y_mult && x_mult <= 100 => Some((a_presses, x_mult)),
+					_ => None
+				}
+			}).min_by_key(|&(a_presses, b_presses)| (a_presses * A_COST) + (b_presses * B_COST))
+		}
+
+		// machines whose buttons point the same way, so there can be several ways to reach the prize
+		const PARALLEL: &str = "Button A: X+2, Y+2
+Button B: X+1, Y+1
+Prize: X=4, Y=4
+
+Button A: X+4, Y+4
+Button B: X+1, Y+1
+Prize: X=8, Y=8
+
+Button A: X+2, Y+2
+Button B: X+1, Y+1
+Prize: X=4, Y=5
+
+Button A: X+3, Y+6
+Button B: X+1, Y+2
+Prize: X=300, Y=600
+
+Button A: X+5, Y+10
+Button B: X+3, Y+6
+Prize: X=31, Y=62";
+		let parallel_machines = parse_machines(PARALLEL)?;
+		assert_eq!(
+			vec![Some((0, 4)), Some((2, 0)), None, Some((67, 99)), Some((2, 7))],
+			parallel_machines.iter().map(|claw_machine| solve_presses(claw_machine, 100)).collect::<Vec<_>>()
+		);
+		assert_eq!(4 + 6 + 300 + 13, part1(PARALLEL)?);
+
+		parse_machines(TEST)?.iter().chain(&parallel_machines).for_each(|claw_machine| {
+			assert_eq!(brute_force_presses(claw_machine), solve_presses(claw_machine, 100));
+		});
+		Ok(())
+	}
+
+	#[test]
+	fn test_large_coordinates() -> Result<()> {
+		// by * px is around 2 * 10^22 here, well past what fits in an i64
+		const LARGE: &str = "Button A: X+1000000000, Y+3
+Button B: X+5, Y+1000000000
+Prize: X=150000, Y=10000000060000";
+		let prize_offset = 20000000000000;
+		assert_eq!(vec![Some((20000, 30000))], solve_machines(LARGE, prize_offset)?);
+
+		// the only solution here needs -1 presses of A
+		let claw_machine = ClawMachine {
+			a: ButtonDelta { x: 2, y: 1 },
+			b: ButtonDelta { x: 1, y: 2 },
+			prize: PrizeLocation { x: 0, y: 3 }
+		};
+		assert_eq!(None, solve_presses(&claw_machine, u64::MAX));
+		Ok(())
+	}
+
+	#[test]
+	fn test_real_solution() -> Result<()> {
+		let claw_machines = parse_machines(TEST)?;
+
+		// the first machine has an integer solution, which the real solution should agree with
+		assert_eq!((80.0, 40.0), real_solution(&claw_machines[0], 0));
+
+		// the second machine has no integer solution at all
+		let (a_presses, b_presses) = real_solution(&claw_machines[1], 0);
+		assert_ne!(0.0, a_presses.fract());
+		assert_ne!(0.0, b_presses.fract());
+		Ok(())
+	}
 }
@@ -1,12 +1,14 @@
-use std::ops::{Add, ControlFlow, Mul};
+use std::ops::{Add, Mul};
 use anyhow::*;
 use grid::Grid;
+use itertools::Itertools;
 use nom::bytes::complete::tag;
 use nom::character::complete::{char, digit1, line_ending};
 use nom::combinator::{all_consuming, map, map_res, opt, recognize};
 use nom::{Finish, IResult};
 use nom::multi::separated_list1;
 use nom::sequence::{preceded, separated_pair};
+use num::Integer;
 
 fn parse_u64(input: &str) -> IResult<&str, u64> {
 	map_res(digit1, |num: &str| num.parse())(input)
@@ -111,6 +113,8 @@ fn parse(input: &str) -> Vec<RobotInfo> {
 	result
 }
 
+pub struct Day14;
+
 pub fn part1(input: &str) -> Result<u64> {
 	const GRID_ROW_LEN: u64 = 101;
 	const GRID_COL_LEN: u64 = 103;
@@ -119,17 +123,23 @@ pub fn part1(input: &str) -> Result<u64> {
 }
 
 fn part1_with_grid_size(input: &str, grid_row_len: u64, grid_col_len: u64) -> Result<u64> {
-	let robots = parse(input);
-
 	const MAX_TIME: u64 = 100;
 
-	let quadrants_product = robots.into_iter().filter_map(|robot| {
-		let distance_travelled = robot.vel * MAX_TIME;
-		let new_position = distance_travelled + robot.pos.into();
-		let normalized_position = NormalizedPosition {
-			x: new_position.x.rem_euclid(grid_row_len as i64) as u64,
-			y: new_position.y.rem_euclid(grid_col_len as i64) as u64,
-		};
+	part1_with_params(input, grid_row_len, grid_col_len, MAX_TIME)
+}
+
+// safety factor after the given number of seconds
+pub fn part1_with_params(input: &str, grid_row_len: u64, grid_col_len: u64, time: u64) -> Result<u64> {
+	Ok(quadrant_counts(input, grid_row_len, grid_col_len, time)?.into_iter().product())
+}
+
+// robots in the top left, top right, bottom left and bottom right quadrants after the given number of seconds,
+// ignoring any on the center lines
+pub fn quadrant_counts(input: &str, grid_row_len: u64, grid_col_len: u64, time: u64) -> Result<[u64; 4]> {
+	let robots = parse(input);
+
+	let counts = robots.into_iter().filter_map(|robot| {
+		let normalized_position = position_after(&robot, time, grid_row_len, grid_col_len);
 
 		if normalized_position.x < grid_row_len / 2 && normalized_position.y < grid_col_len / 2 {
 			Some(0)
@@ -150,33 +160,89 @@ fn part1_with_grid_size(input: &str, grid_row_len: u64, grid_col_len: u64) -> Re
 			3 => [quad0, quad1, quad2, quad3 + 1],
 			_ => [quad0, quad1, quad2, quad3]
 		}
-	}).into_iter().product();
+	});
 
-	Ok(quadrants_product)
+	Ok(counts)
 }
 
-pub fn part2(input: &str) -> Result<u64> {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TreeDetector {
+	// first tick with a horizontal run of robots longer than the given length
+	LongestRun(u64),
+	// tick where the robots are most tightly clustered on both axes
+	MinVariance,
+}
+
+impl TreeDetector {
+	// roughly a tenth of the width, so the threshold grows along with the grid
+	pub const fn scaled_longest_run(grid_row_len: u64) -> Self {
+		TreeDetector::LongestRun(grid_row_len / 10)
+	}
+}
+
+fn position_after(robot: &RobotInfo, time: u64, grid_row_len: u64, grid_col_len: u64) -> NormalizedPosition {
+	let distance_travelled = robot.vel * time;
+	let new_position = distance_travelled + robot.pos.into();
+	NormalizedPosition {
+		x: new_position.x.rem_euclid(grid_row_len as i64) as u64,
+		y: new_position.y.rem_euclid(grid_col_len as i64) as u64,
+	}
+}
+
+// where a single robot (in input order) ends up after the given number of seconds, as (x, y)
+pub fn position_at(input: &str, robot_ix: usize, time: u64, grid_row_len: u64, grid_col_len: u64) -> Result<(u64, u64)> {
 	let robots = parse(input);
+	let Some(robot) = robots.get(robot_ix) else {
+		bail!("robot {} not found, only {} robots", robot_ix, robots.len());
+	};
+
+	let NormalizedPosition { x, y } = position_after(robot, time, grid_row_len, grid_col_len);
+	Ok((x, y))
+}
+
+pub fn part2(input: &str) -> Result<u64> {
 	const GRID_ROW_LEN: u64 = 101;
 	const GRID_COL_LEN: u64 = 103;
 
-	const RUN_LEN_REQUIRED: u64 = 10;
+	part2_with_grid_size(input, GRID_ROW_LEN, GRID_COL_LEN)
+}
+
+pub fn part2_with_grid_size(input: &str, grid_row_len: u64, grid_col_len: u64) -> Result<u64> {
+	part2_with_detector(input, grid_row_len, grid_col_len, TreeDetector::MinVariance)
+}
 
-	let tree_time = (0..).try_for_each(|time: u64| {
-		let mut grid: Grid<_> = Grid::init(GRID_COL_LEN as usize, GRID_ROW_LEN as usize, false);
+// first tick with a horizontal run of robots longer than the threshold, scaled to the grid if not given
+pub fn part2_with_run_threshold(input: &str, grid_row_len: u64, grid_col_len: u64, run_len_required: Option<u64>) -> Result<u64> {
+	let detector = run_len_required.map_or(TreeDetector::scaled_longest_run(grid_row_len), TreeDetector::LongestRun);
+
+	part2_with_detector(input, grid_row_len, grid_col_len, detector)
+}
+
+pub fn part2_with_detector(input: &str, grid_row_len: u64, grid_col_len: u64, detector: TreeDetector) -> Result<u64> {
+	let robots = parse(input);
+
+	match detector {
+		TreeDetector::LongestRun(run_len_required) => find_long_run(&robots, grid_row_len, grid_col_len, run_len_required),
+		TreeDetector::MinVariance => find_min_variance(&robots, grid_row_len, grid_col_len),
+	}
+}
+
+// every robot is back where it started after this many ticks, so the configurations repeat from then on
+pub fn configuration_period(grid_row_len: u64, grid_col_len: u64) -> u64 {
+	grid_row_len.lcm(&grid_col_len)
+}
+
+fn find_long_run(robots: &[RobotInfo], grid_row_len: u64, grid_col_len: u64, run_len_required: u64) -> Result<u64> {
+	// there's no need to look past the first full cycle of configurations
+	(0..configuration_period(grid_row_len, grid_col_len)).find(|&time| {
+		let mut grid: Grid<_> = Grid::init(grid_col_len as usize, grid_row_len as usize, false);
 
 		robots.iter().for_each(|robot| {
-			let distance_travelled = robot.vel * time;
-			let new_position = distance_travelled + robot.pos.into();
-			let normalized_position = NormalizedPosition {
-				x: new_position.x.rem_euclid(GRID_ROW_LEN as i64) as u64,
-				y: new_position.y.rem_euclid(GRID_COL_LEN as i64) as u64,
-			};
+			let normalized_position = position_after(robot, time, grid_row_len, grid_col_len);
 			*grid.get_mut(normalized_position.y, normalized_position.x).unwrap() = true;
 		});
 
-		
-		let long_run_found = grid.iter_rows().map(|row| {
+		grid.iter_rows().map(|row| {
 			let mut longest_run = 0;
 			let mut curr_run = 0;
 			row.for_each(|&cell| {
@@ -190,21 +256,49 @@ pub fn part2(input: &str) -> Result<u64> {
 				}
 			});
 			longest_run
-		}).any(|run| run > RUN_LEN_REQUIRED);
-		if long_run_found {
-			// println!("time t={:?}", time);
-			// grid.iter_rows().for_each(|row| {
-			// 	println!("{}", row.map(|&cell| if cell { '█' } else { ' ' }).join(" "));
-			// });
-			// println!();
-
-			ControlFlow::Break(time)
-		} else {
-			ControlFlow::Continue(())
-		}
+		}).any(|run| run > run_len_required)
+	}).ok_or_else(|| anyhow!("no horizontal run of more than {} robots", run_len_required))
+}
+
+fn find_min_variance(robots: &[RobotInfo], grid_row_len: u64, grid_col_len: u64) -> Result<u64> {
+	if robots.is_empty() {
+		bail!("no robots to cluster");
+	}
+
+	// x positions repeat every grid_row_len ticks and y positions every grid_col_len ticks,
+	// so find the tightest tick on each axis independently, then the tick where both line up
+	fn scaled_variance(values: impl Iterator<Item = u64>) -> u64 {
+		// n^2 * variance, avoids floats without changing which tick is smallest
+		let (count, sum, sum_squares) = values.fold((0, 0, 0), |(count, sum, sum_squares), val| {
+			(count + 1, sum + val, sum_squares + val * val)
+		});
+		count * sum_squares - sum * sum
+	}
+
+	let best_x_time = (0..grid_row_len).min_by_key(|&time| {
+		scaled_variance(robots.iter().map(|robot| position_after(robot, time, grid_row_len, grid_col_len).x))
+	}).unwrap();
+	let best_y_time = (0..grid_col_len).min_by_key(|&time| {
+		scaled_variance(robots.iter().map(|robot| position_after(robot, time, grid_row_len, grid_col_len).y))
+	}).unwrap();
+
+	(best_x_time..configuration_period(grid_row_len, grid_col_len)).step_by(grid_row_len as usize)
+		.find(|time| time % grid_col_len == best_y_time)
+		.ok_or_else(|| anyhow!("tightest ticks on each axis never coincide"))
+}
+
+pub fn render_at(input: &str, time: u64, grid_row_len: u64, grid_col_len: u64) -> String {
+	let robots = parse(input);
+
+	let mut grid: Grid<_> = Grid::init(grid_col_len as usize, grid_row_len as usize, false);
+	robots.iter().for_each(|robot| {
+		let normalized_position = position_after(robot, time, grid_row_len, grid_col_len);
+		*grid.get_mut(normalized_position.y, normalized_position.x).unwrap() = true;
 	});
 
-	Ok(tree_time.break_value().unwrap())
+	grid.iter_rows().map(|row| {
+		row.map(|&cell| if cell { '#' } else { ' ' }).collect::<String>()
+	}).join("\n")
 }
 
 #[cfg(test)]
@@ -232,4 +326,126 @@ p=9,5 v=-3,-3";
 		assert_eq!(12, part1_with_grid_size(TEST, TEST_GRID_ROW_LEN, TEST_GRID_COL_LEN)?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_safety_factor_over_time() -> Result<()> {
+		assert_eq!(12, part1_with_params(TEST, 11, 7, 100)?);
+		// at the start the top right quadrant is empty
+		assert_eq!(0, part1_with_params(TEST, 11, 7, 0)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_quadrant_counts() -> Result<()> {
+		let counts = quadrant_counts(TEST, 11, 7, 100)?;
+		assert_eq!([1, 3, 4, 1], counts);
+		assert_eq!(12, counts.into_iter().product::<u64>());
+		Ok(())
+	}
+
+	#[test]
+	fn test_position_at() -> Result<()> {
+		// p=2,4 v=2,-3 from the puzzle description, wrapping off the top and right edges
+		let positions = (0..=5).map(|time| position_at(TEST, 10, time, 11, 7)).collect::<Result<Vec<_>>>()?;
+		assert_eq!(vec![(2, 4), (4, 1), (6, 5), (8, 2), (10, 6), (1, 3)], positions);
+		assert!(position_at(TEST, 12, 0, 11, 7).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_render_at_start() {
+		// two robots start on the same cell
+		let rendered = render_at(TEST, 0, 11, 7);
+		assert_eq!(11, rendered.chars().filter(|&chr| chr == '#').count());
+		assert_eq!(7, rendered.lines().count());
+		assert!(rendered.lines().all(|line| line.len() == 11));
+	}
+
+	#[test]
+	fn test_render_at_tree() {
+		let rendered = render_at(TREE, TREE_TICK, 101, 103);
+		assert!(rendered.contains(&"#".repeat(15)));
+	}
+
+	// 40 robots that line up into a 15 wide bar above a 5x5 block at t=4321 on the full size grid
+	const TREE_TICK: u64 = 4321;
+	const TREE: &str = "p=72,36 v=-72,59
+p=5,10 v=81,95
+p=23,24 v=68,36
+p=59,3 v=-36,-30
+p=4,86 v=90,-34
+p=0,83 v=-25,89
+p=82,91 v=-81,70
+p=17,43 v=17,-22
+p=5,23 v=21,77
+p=14,60 v=3,2
+p=47,96 v=-69,-32
+p=36,58 v=-42,-19
+p=78,91 v=-8,-33
+p=0,56 v=-7,63
+p=26,14 v=63,34
+p=16,71 v=-61,-58
+p=82,1 v=43,72
+p=92,12 v=71,-29
+p=3,85 v=-57,-97
+p=26,58 v=68,-82
+p=65,13 v=-68,54
+p=63,5 v=-13,-92
+p=47,12 v=-78,-29
+p=22,45 v=-47,-2
+p=55,2 v=5,51
+p=52,28 v=14,57
+p=13,74 v=-75,66
+p=24,8 v=77,-71
+p=36,6 v=50,52
+p=63,67 v=65,64
+p=17,96 v=-6,-53
+p=14,3 v=-75,93
+p=14,13 v=26,33
+p=60,11 v=74,-50
+p=95,38 v=-30,17
+p=78,38 v=61,-44
+p=15,21 v=26,-27
+p=2,90 v=30,-34
+p=59,24 v=-73,-68
+p=72,19 v=-77,-28";
+
+	#[test]
+	fn test_part_two_min_variance() -> Result<()> {
+		assert_eq!(TREE_TICK, part2_with_grid_size(TREE, 101, 103)?);
+		assert_eq!(TREE_TICK, part2(TREE)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_part_two_run_threshold() -> Result<()> {
+		assert_eq!(TreeDetector::LongestRun(10), TreeDetector::scaled_longest_run(101));
+		assert_eq!(TREE_TICK, part2_with_run_threshold(TREE, 101, 103, None)?);
+		assert_eq!(TREE_TICK, part2_with_run_threshold(TREE, 101, 103, Some(10))?);
+		// the bar is only 15 robots wide
+		assert!(part2_with_run_threshold(TREE, 101, 103, Some(20)).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_configuration_period() -> Result<()> {
+		assert_eq!(10403, configuration_period(101, 103));
+		assert_eq!(12, configuration_period(4, 6));
+
+		let period = configuration_period(101, 103);
+		assert!(part2(TREE)? < period);
+		assert!(part2_with_run_threshold(TREE, 101, 103, Some(10))? < period);
+		// every robot is back at its start after a full period
+		assert_eq!(render_at(TREE, 0, 101, 103), render_at(TREE, period, 101, 103));
+		Ok(())
+	}
+
+	#[test]
+	fn test_part_two_detectors_agree() -> Result<()> {
+		assert_eq!(
+			part2_with_detector(TREE, 101, 103, TreeDetector::MinVariance)?,
+			part2_with_detector(TREE, 101, 103, TreeDetector::LongestRun(10))?
+		);
+		Ok(())
+	}
 }
@@ -8,15 +8,27 @@ use nom::combinator::{all_consuming, opt, value};
 use nom::{Finish, IResult};
 use nom::multi::{fold_many1, many1};
 use nom::sequence::{separated_pair, terminated};
+use crate::direction::Direction;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-enum MapCell {
+pub enum MapCell {
 	Robot,
 	Box,
 	Wall,
 	Empty
 }
 
+impl From<MapCell> for char {
+	fn from(value: MapCell) -> Self {
+		match value {
+			MapCell::Robot => '@',
+			MapCell::Box => 'O',
+			MapCell::Wall => '#',
+			MapCell::Empty => '.',
+		}
+	}
+}
+
 fn parse_map_cell(input: &str) -> IResult<&str, MapCell> {
 	alt((
 		value(MapCell::Robot, char('@')),
@@ -37,31 +49,6 @@ fn parse_map(input: &str) -> IResult<&str, Vec<Vec<MapCell>>> {
 	many1(parse_map_row)(input)
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-enum Direction {
-	North,
-	East,
-	South,
-	West,
-}
-impl Direction {
-	pub const fn get_offset(&self) -> (isize, isize) {
-		match self {
-			Direction::North => (-1, 0),
-			Direction::East => (0, 1),
-			Direction::South => (1, 0),
-			Direction::West => (0, -1),
-		}
-	}
-
-	pub fn offset_from(&self, (row, col): (impl TryInto<isize>, impl TryInto<isize>)) -> Option<(isize, isize)> {
-		let row_isize = row.try_into().ok()?;
-		let col_isize = col.try_into().ok()?;
-		let (row_offset, col_offset) = self.get_offset();
-		Some((row_isize + row_offset, col_isize + col_offset))
-	}
-}
-
 fn parse_direction(input: &str) -> IResult<&str, Direction> {
 	alt((
 		value(Direction::North, char('^')),
@@ -94,57 +81,112 @@ fn parse(input: &str) -> (Vec<Vec<MapCell>>, Vec<Direction>) {
 	result
 }
 
+pub struct Day15;
+
 pub fn part1(input: &str) -> Result<u64> {
+	Ok(box_positions(input)?.into_iter().map(gps_coordinate).sum())
+}
+
+// (row, col) of every box once the robot is done moving
+pub fn box_positions(input: &str) -> Result<Vec<(usize, usize)>> {
+	let (map, directions) = parse(input);
+	let mut map: Grid<_> = map.into();
+
+	let mut robot_coords = find_robot(&map);
+	directions.into_iter().for_each(|direction| {
+		move_robot(&mut map, &mut robot_coords, direction);
+	});
+
+	Ok(box_cells(&map))
+}
+
+fn gps_coordinate((row, col): (usize, usize)) -> u64 {
+	(col + (100 * row)) as u64
+}
+
+// the warehouse after every robot move, starting with the initial layout
+pub fn part1_states(input: &str) -> Result<Vec<Grid<MapCell>>> {
 	let (map, directions) = parse(input);
 	let mut map: Grid<_> = map.into();
 
-	let (mut robot_row, mut robot_col) = map.indexed_iter().find_map(|(grid_coords, &grid_cell)| {
+	let mut robot_coords = find_robot(&map);
+	let mut states = vec![map.clone()];
+	directions.into_iter().for_each(|direction| {
+		move_robot(&mut map, &mut robot_coords, direction);
+		states.push(map.clone());
+	});
+
+	Ok(states)
+}
+
+// the direction the robot tried to move in, and every cell that changed as a result
+pub type MoveDiff = (Direction, Vec<((isize, isize), MapCell)>);
+
+// the initial layout, then only the cells that changed with every robot move
+pub fn part1_diffs(input: &str) -> Result<(Grid<MapCell>, Vec<MoveDiff>)> {
+	let (map, directions) = parse(input);
+	let initial_map: Grid<_> = map.into();
+
+	let mut map = initial_map.clone();
+	let mut robot_coords = find_robot(&map);
+	let diffs = directions.into_iter().map(|direction| {
+		(direction, move_robot(&mut map, &mut robot_coords, direction))
+	}).collect();
+
+	Ok((initial_map, diffs))
+}
+
+fn find_robot(map: &Grid<MapCell>) -> (isize, isize) {
+	map.indexed_iter().find_map(|((grid_row, grid_col), &grid_cell)| {
 		if grid_cell == MapCell::Robot {
-			Some(grid_coords)
+			Some((grid_row as isize, grid_col as isize))
 		} else {
 			None
 		}
-	}).unwrap();
+	}).unwrap()
+}
 
-	directions.into_iter().for_each(|direction| {
-		let mut cell_changes = Vec::new();
+// moves the robot one step, pushing any boxes in the way, and returns the cells that changed
+fn move_robot(map: &mut Grid<MapCell>, robot_coords: &mut (isize, isize), direction: Direction) -> Vec<((isize, isize), MapCell)> {
+	let mut cell_changes = vec![(*robot_coords, MapCell::Empty)];
 
-		let mut curr_cell_coords = (robot_row as isize, robot_col as isize);
-		let mut curr_cell = MapCell::Robot;
-		loop {
-			let (next_cell_row, next_cell_col) = direction.offset_from(curr_cell_coords).unwrap();
-			let next_cell = *map.get(next_cell_row, next_cell_col).unwrap();
+	let mut curr_cell_coords = *robot_coords;
+	let mut curr_cell = MapCell::Robot;
+	loop {
+		let (next_cell_row, next_cell_col) = direction.offset_from(curr_cell_coords).unwrap();
+		let next_cell = *map.get(next_cell_row, next_cell_col).unwrap();
 
-			if next_cell == MapCell::Wall {
-				cell_changes.clear();
+		if next_cell == MapCell::Wall {
+			cell_changes.clear();
+			break;
+		} else {
+			cell_changes.push(((next_cell_row, next_cell_col), curr_cell));
+			if next_cell != MapCell::Box {
 				break;
-			} else {
-				cell_changes.push(((next_cell_row, next_cell_col), curr_cell));
-				if next_cell != MapCell::Box {
-					break;
-				}
 			}
+		}
 
-			curr_cell_coords = (next_cell_row, next_cell_col);
-			curr_cell = next_cell;
+		curr_cell_coords = (next_cell_row, next_cell_col);
+		curr_cell = next_cell;
+	}
+	cell_changes.iter().for_each(|&((cell_row, cell_col), cell_new_contents)| {
+		*map.get_mut(cell_row, cell_col).unwrap() = cell_new_contents;
+		if cell_new_contents == MapCell::Robot {
+			*robot_coords = (cell_row, cell_col);
 		}
-		cell_changes.into_iter().for_each(|((cell_row, cell_col), cell_new_contents)| {
-			*map.get_mut(cell_row, cell_col).unwrap() = cell_new_contents;
-			if cell_new_contents == MapCell::Robot {
-				(robot_row, robot_col) = (cell_row as usize, cell_col as usize);
-			}
-		});
 	});
 
-	let gps_sum = map.indexed_iter().filter_map(|((cell_row, cell_col), &map_cell)| {
+	cell_changes
+}
+
+fn box_cells(map: &Grid<MapCell>) -> Vec<(usize, usize)> {
+	map.indexed_iter().filter_map(|(cell_coords, &map_cell)| {
 		if map_cell == MapCell::Box {
-			Some((cell_col + (100 * cell_row)) as u64)
+			Some(cell_coords)
 		} else {
 			None
 		}
-	}).sum();
-
-	Ok(gps_sum)
+	}).collect()
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -155,6 +197,17 @@ enum MapCellPartTwo {
 	Wall,
 	Empty
 }
+impl From<MapCellPartTwo> for char {
+	fn from(value: MapCellPartTwo) -> Self {
+		match value {
+			MapCellPartTwo::Robot => '@',
+			MapCellPartTwo::BoxLeft => '[',
+			MapCellPartTwo::BoxRight => ']',
+			MapCellPartTwo::Wall => '#',
+			MapCellPartTwo::Empty => '.',
+		}
+	}
+}
 impl MapCellPartTwo {
 	pub const fn other_half(&self) -> Option<MapCellPartTwo> {
 		match self {
@@ -172,16 +225,26 @@ impl MapCellPartTwo {
 	}
 }
 
-pub fn part2(input: &str) -> Result<u64> {
-	let (map, directions) = parse(input);
-	let mut map: Grid<_> = map.into_iter().map(|row| row.into_iter().flat_map(|cell| {
+// every cell becomes two cells side by side, boxes are split into their left and right halves
+fn widen_map(map: &[Vec<MapCell>]) -> Vec<Vec<MapCellPartTwo>> {
+	map.iter().map(|row| row.iter().flat_map(|cell| {
 		match cell {
-			MapCell::Robot => vec![MapCellPartTwo::Robot, MapCellPartTwo::Empty],
-			MapCell::Box => vec![MapCellPartTwo::BoxLeft, MapCellPartTwo::BoxRight],
-			MapCell::Wall => vec![MapCellPartTwo::Wall, MapCellPartTwo::Wall],
-			MapCell::Empty => vec![MapCellPartTwo::Empty, MapCellPartTwo::Empty],
+			MapCell::Robot => [MapCellPartTwo::Robot, MapCellPartTwo::Empty],
+			MapCell::Box => [MapCellPartTwo::BoxLeft, MapCellPartTwo::BoxRight],
+			MapCell::Wall => [MapCellPartTwo::Wall, MapCellPartTwo::Wall],
+			MapCell::Empty => [MapCellPartTwo::Empty, MapCellPartTwo::Empty],
 		}
-	}).collect()).collect_vec().into();
+	}).collect()).collect_vec()
+}
+
+pub fn part2(input: &str) -> Result<u64> {
+	Ok(wide_box_positions(input)?.into_iter().map(gps_coordinate).sum())
+}
+
+// (row, col) of the left half of every box in the widened warehouse once the robot is done moving
+pub fn wide_box_positions(input: &str) -> Result<Vec<(usize, usize)>> {
+	let (map, directions) = parse(input);
+	let mut map: Grid<_> = widen_map(&map).into();
 
 	let (robot_row, robot_col) = map.indexed_iter().find_map(|(grid_coords, &grid_cell)| {
 		if grid_cell == MapCellPartTwo::Robot {
@@ -235,15 +298,15 @@ pub fn part2(input: &str) -> Result<u64> {
 
 	let _ = directions.into_iter().fold((robot_row as isize, robot_col as isize), |robot_loc, direction| try_push(robot_loc, direction));
 
-	let gps_sum = map.indexed_iter().filter_map(|((cell_row, cell_col), &map_cell)| {
+	let box_positions = map.indexed_iter().filter_map(|(cell_coords, &map_cell)| {
 		if map_cell == MapCellPartTwo::BoxLeft {
-			Some((cell_col + (100 * cell_row)) as u64)
+			Some(cell_coords)
 		} else {
 			None
 		}
-	}).sum();
+	}).collect();
 
-	Ok(gps_sum)
+	Ok(box_positions)
 }
 
 #[cfg(test)]
@@ -300,4 +363,51 @@ v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^";
 		assert_eq!(9021, part2(LARGE_TEST)?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_box_positions() -> Result<()> {
+		let positions = box_positions(SMALL_TEST)?;
+		assert_eq!(vec![(1, 5), (1, 6), (3, 6), (4, 3), (5, 4), (6, 4)], positions);
+		assert_eq!(2028, positions.into_iter().map(gps_coordinate).sum::<u64>());
+
+		let wide_positions = wide_box_positions(LARGE_TEST)?;
+		assert_eq!(21, wide_positions.len());
+		assert_eq!(9021, wide_positions.into_iter().map(gps_coordinate).sum::<u64>());
+		Ok(())
+	}
+
+	#[test]
+	fn test_part_one_states() -> Result<()> {
+		let states = part1_states(SMALL_TEST)?;
+		// the initial layout plus one state per move
+		assert_eq!(16, states.len());
+		assert_eq!(part1(SMALL_TEST)?, box_cells(states.last().unwrap()).into_iter().map(gps_coordinate).sum());
+		// the robot is never left behind in its old cell
+		assert!(states.iter().all(|state| state.iter().filter(|&&cell| cell == MapCell::Robot).count() == 1));
+		Ok(())
+	}
+
+	#[test]
+	fn test_part_one_diffs() -> Result<()> {
+		let (mut map, diffs) = part1_diffs(SMALL_TEST)?;
+		assert_eq!(15, diffs.len());
+		// the first move is west into a wall, so nothing changes
+		assert_eq!((Direction::West, Vec::new()), diffs[0]);
+		diffs.into_iter().flat_map(|(_, changes)| changes).for_each(|((row, col), cell)| {
+			*map.get_mut(row, col).unwrap() = cell;
+		});
+		assert_eq!(part1_states(SMALL_TEST)?.last(), Some(&map));
+		Ok(())
+	}
+
+	#[test]
+	fn test_widen_map() {
+		let map = vec![vec![MapCell::Wall, MapCell::Empty, MapCell::Box, MapCell::Robot]];
+		assert_eq!(vec![vec![
+			MapCellPartTwo::Wall, MapCellPartTwo::Wall,
+			MapCellPartTwo::Empty, MapCellPartTwo::Empty,
+			MapCellPartTwo::BoxLeft, MapCellPartTwo::BoxRight,
+			MapCellPartTwo::Robot, MapCellPartTwo::Empty,
+		]], widen_map(&map));
+	}
 }
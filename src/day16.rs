@@ -1,23 +1,36 @@
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
-use std::collections::hash_map::Entry;
 use ahash::{AHashMap, AHashSet};
 use anyhow::*;
 use grid::Grid;
+use itertools::Itertools;
 use nom::character::complete::{line_ending, one_of};
 use nom::combinator::{all_consuming, map_res, opt};
 use nom::{Finish, IResult};
 use nom::multi::many1;
 use nom::sequence::terminated;
+use crate::direction::Direction;
+use crate::pathfind::MinScored;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum MapCell {
+pub enum MapCell {
 	Start,
 	End,
 	Wall,
 	Empty,
 }
 
+impl From<MapCell> for char {
+	fn from(value: MapCell) -> Self {
+		match value {
+			MapCell::Start => 'S',
+			MapCell::End => 'E',
+			MapCell::Wall => '#',
+			MapCell::Empty => '.',
+		}
+	}
+}
+
 fn parse_cell(input: &str) -> IResult<&str, MapCell> {
 	map_res(one_of("SE#."), |chr| {match chr {
 		'S' => Ok(MapCell::Start),
@@ -35,103 +48,103 @@ fn parse_line(input: &str) -> IResult<&str, Vec<MapCell>> {
 	)(input)
 }
 
-fn parse(input: &str) -> Vec<Vec<MapCell>> {
-	let (_, result) = all_consuming(many1(parse_line))(input).finish().unwrap();
-	result
+pub fn parse_input(input: &str) -> Result<Grid<MapCell>> {
+	let (_, result) = all_consuming(many1(parse_line))(input).finish()
+		.map_err(|err| anyhow!("could not parse input at {:?}: {:?}", err.input.lines().next().unwrap_or_default(), err.code))?;
+	Ok(result.into())
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-enum Direction {
-	North,
-	East,
-	South,
-	West,
+pub struct Day16;
+
+pub fn part1(input: &str) -> Result<u64> {
+	part1_facing(input, Direction::East)
 }
-impl Direction {
-	pub const fn get_offset(&self) -> (isize, isize) {
-		match self {
-			Direction::North => (-1, 0),
-			Direction::East => (0, 1),
-			Direction::South => (1, 0),
-			Direction::West => (0, -1),
-		}
-	}
 
-	pub fn offset_from(&self, (row, col): (impl TryInto<isize>, impl TryInto<isize>)) -> Option<(isize, isize)> {
-		let row_isize = row.try_into().ok()?;
-		let col_isize = col.try_into().ok()?;
-		let (row_offset, col_offset) = self.get_offset();
-		Some((row_isize + row_offset, col_isize + col_offset))
-	}
+pub fn part1_facing(input: &str, start_dir: Direction) -> Result<u64> {
+	min_end_cost(&parse_input(input)?, start_dir)
+}
+
+// part1 on an already parsed map, so benchmarks can measure the search on its own
+pub fn part1_grid(map: &Grid<MapCell>) -> Result<u64> {
+	min_end_cost(map, Direction::East)
+}
+
+fn min_end_cost(map: &Grid<MapCell>, start_dir: Direction) -> Result<u64> {
+	let search = search(map, start_dir);
+	let Some(min_cost) = search.end_states(map).first().map(|state| search.weights[state]) else {
+		bail!("No path found?!")
+	};
+	Ok(min_cost)
+}
+
+// steps and turns taken along one of the best paths, so the score is steps + 1000 * turns
+pub fn part1_breakdown(input: &str) -> Result<(u64, u64)> {
+	let map = parse_input(input)?;
+	let search = search(&map, Direction::East);
+	let Some(&end_state) = search.end_states(&map).first() else {
+		bail!("No path found?!")
+	};
 
-	pub const fn perpendicular(&self) -> [Direction; 2] {
-		match self {
-			Direction::North | Direction::South => [
-				Direction::East, Direction::West
-			],
-			Direction::East | Direction::West => [
-				Direction::North, Direction::South
-			]
+	// any one predecessor chain back to the start is a best path, every move either steps or turns
+	let (mut steps, mut turns) = (0, 0);
+	let mut state = end_state;
+	while let Some(&prev_state) = search.predecessors.get(&state).and_then(|preds| preds.first()) {
+		if prev_state.1 != state.1 {
+			turns += 1;
+		} else {
+			steps += 1;
 		}
+		state = prev_state;
 	}
+	Ok((steps, turns))
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-struct SearchNode {
-	est_cost: u64,
-	real_cost: u64,
+// lowest score to reach every reachable tile, facing each direction, from the start facing East
+pub fn costs_from_start(input: &str) -> Result<AHashMap<State, u64>> {
+	let map = parse_input(input)?;
 
-	pos: (isize, isize),
-	facing: Direction
+	Ok(search(&map, Direction::East).weights)
 }
-impl PartialOrd for SearchNode {
-	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-		// inverted, lower weight is better
-		Some(self.cmp(other))
-	}
+
+type State = ((isize, isize), Direction);
+
+struct Search {
+	weights: AHashMap<State, u64>,
+	predecessors: AHashMap<State, Vec<State>>,
 }
-impl Ord for SearchNode {
-	fn cmp(&self, other: &Self) -> Ordering {
-		// inverted, lower weight is better
-		other.est_cost.cmp(&self.est_cost)
+
+impl Search {
+	// every facing the end is reached with at the lowest score
+	fn end_states(&self, map: &Grid<MapCell>) -> Vec<State> {
+		let end_pos = find_cell(map, MapCell::End);
+		Direction::values().into_iter()
+			.map(|dir| (end_pos, dir))
+			.filter(|state| self.weights.contains_key(state))
+			.min_set_by_key(|state| self.weights[state])
 	}
 }
 
-pub fn part1(input: &str) -> Result<u64> {
-	let map: Grid<_> = parse(input).into();
-	let (start_row, start_col) = map.indexed_iter().find_map(|((row, col), &cell)| {
-		if cell == MapCell::Start {
+fn find_cell(map: &Grid<MapCell>, target: MapCell) -> (isize, isize) {
+	map.indexed_iter().find_map(|((row, col), &cell)| {
+		if cell == target {
 			Some((row as isize, col as isize))
 		} else {
 			None
 		}
-	}).unwrap();
-	let (end_row, end_col) = map.indexed_iter().find_map(|((row, col), &cell)| {
-		if cell == MapCell::End {
-			Some((row as isize, col as isize))
-		} else {
-			None
-		}
-	}).unwrap();
+	}).unwrap()
+}
 
-	fn taxicab_distance((curr_row, curr_col): (isize, isize), (goal_row, goal_col): (isize, isize)) -> u64 {
-		(curr_row.abs_diff(goal_row) + curr_col.abs_diff(goal_col)) as u64
-	}
+// plain dijkstra over (pos, facing), remembering every predecessor that reached a state at its best cost
+fn search(map: &Grid<MapCell>, start_dir: Direction) -> Search {
+	let start_pos = find_cell(map, MapCell::Start);
 
 	let mut queue = BinaryHeap::new();
-	let mut weights = AHashMap::new();
-	weights.insert(((start_row, start_col), Direction::East), 0);
-	queue.push(SearchNode {
-		est_cost: taxicab_distance((start_row, start_col), (end_row, end_col)),
-		real_cost: 0,
-		pos: (start_row, start_col),
-		facing: Direction::East,
-	});
+	let mut weights: AHashMap<State, u64> = AHashMap::new();
+	let mut predecessors: AHashMap<State, Vec<State>> = AHashMap::new();
+	weights.insert((start_pos, start_dir), 0);
+	queue.push(MinScored(0, (start_pos, start_dir)));
 
-	while let Some(SearchNode { real_cost, pos: curr_pos, facing: curr_dir, .. }) = queue.pop() {
-		if curr_pos == (end_row, end_col) {
-			return Ok(real_cost);
-		}
+	while let Some(MinScored(real_cost, (curr_pos, curr_dir))) = queue.pop() {
 		// may insert same node multiple times, skip if already seen with lower cost
 		if let Some(&prev_cost) = weights.get(&(curr_pos, curr_dir)) {
 			if real_cost > prev_cost {
@@ -148,160 +161,122 @@ pub fn part1(input: &str) -> Result<u64> {
 				((curr_pos, new_dir), real_cost + 1000));
 			forward_move.chain(turn_moves)
 		};
-		let _ = successors.into_iter().for_each(|((next_pos, next_dir), next_cost)| {
-			match weights.entry((next_pos, next_dir)) {
-				Entry::Vacant(e) => {
-					e.insert(next_cost);
-					queue.push(SearchNode {
-						est_cost: next_cost + taxicab_distance(next_pos, (end_row, end_col)),
-						real_cost: next_cost,
-						pos: next_pos,
-						facing: next_dir,
-					});
+		successors.into_iter().for_each(|(next_state, next_cost)| {
+			let best_cost = weights.entry(next_state).or_insert(next_cost);
+			match next_cost.cmp(best_cost) {
+				Ordering::Less => {
+					*best_cost = next_cost;
+					predecessors.insert(next_state, vec![(curr_pos, curr_dir)]);
 				},
-				Entry::Occupied(mut e) => {
-					if next_cost < *e.get() {
-						e.insert(next_cost);
-						queue.push(SearchNode {
-							est_cost: next_cost + taxicab_distance(next_pos, (end_row, end_col)),
-							real_cost: next_cost,
-							pos: next_pos,
-							facing: next_dir,
-						});
+				Ordering::Equal => {
+					let preds = predecessors.entry(next_state).or_default();
+					// a fresh entry has no predecessors yet and still needs queueing, otherwise it's already queued
+					let already_queued = !preds.is_empty();
+					preds.push((curr_pos, curr_dir));
+					if already_queued {
+						return;
 					}
-				}
-			};
+				},
+				Ordering::Greater => return,
+			}
+			queue.push(MinScored(next_cost, next_state));
 		});
 	}
 
-	bail!("No path found?!")
+	Search { weights, predecessors }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-struct SearchNodePartTwo {
-	est_cost: u64,
-	real_cost: u64,
-	path: AHashSet<(isize, isize)>,
-	pos: (isize, isize),
-	facing: Direction
-}
-impl PartialOrd for SearchNodePartTwo {
-	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-		// inverted, lower weight is better
-		Some(self.cmp(other))
-	}
-}
-impl Ord for SearchNodePartTwo {
-	fn cmp(&self, other: &Self) -> Ordering {
-		// inverted, lower weight is better
-		other.est_cost.cmp(&self.est_cost)
-	}
+pub fn part2(input: &str) -> Result<u64> {
+	let map = parse_input(input)?;
+
+	Ok(best_path_tiles(&map).len() as u64)
 }
 
-pub fn part2(input: &str) -> Result<u64> {
-	let map: Grid<_> = parse(input).into();
-	let start_pos = map.indexed_iter().find_map(|((row, col), &cell)| {
-		if cell == MapCell::Start {
-			Some((row as isize, col as isize))
-		} else {
-			None
-		}
-	}).unwrap();
-	let (end_row, end_col) = map.indexed_iter().find_map(|((row, col), &cell)| {
-		if cell == MapCell::End {
-			Some((row as isize, col as isize))
-		} else {
-			None
-		}
-	}).unwrap();
+pub fn part2_paths(input: &str) -> Result<Vec<Vec<(isize, isize)>>> {
+	let map = parse_input(input)?;
 
-	let mut min_cost = None;
-	let mut found_paths = Vec::new();
-	let taxicab_distance = |(curr_row, curr_col): (isize, isize)| {
-		(curr_row.abs_diff(end_row) + curr_col.abs_diff(end_col)) as u64
-	};
+	Ok(best_paths(&map))
+}
 
-	let mut queue = BinaryHeap::new();
-	let mut weights = AHashMap::new();
-	weights.insert((start_pos, Direction::East), 0);
-	queue.push(SearchNodePartTwo {
-		est_cost: taxicab_distance(start_pos),
-		real_cost: 0,
-		path: AHashSet::from([start_pos]),
-		pos: start_pos,
-		facing: Direction::East,
-	});
+fn best_path_tiles(map: &Grid<MapCell>) -> AHashSet<(isize, isize)> {
+	let search = search(map, Direction::East);
+	let end_states = search.end_states(map);
 
-	while let Some(SearchNodePartTwo { real_cost,  pos: curr_pos, facing: curr_dir, path: curr_path, .. }) = queue.pop() {
-		if let Some(min_cost) = min_cost {
-			if min_cost < real_cost {
-				break;
+	// walk back from every best way of reaching the end over all minimum-cost predecessors
+	let mut seen_states: AHashSet<State> = end_states.iter().copied().collect();
+	let mut to_visit = end_states;
+	while let Some(state) = to_visit.pop() {
+		search.predecessors.get(&state).into_iter().flatten().for_each(|&prev_state| {
+			if seen_states.insert(prev_state) {
+				to_visit.push(prev_state);
 			}
+		});
+	}
+
+	seen_states.into_iter().map(|(pos, _)| pos).collect()
+}
+
+fn best_paths(map: &Grid<MapCell>) -> Vec<Vec<(isize, isize)>> {
+	let search = search(map, Direction::East);
+	let end_states = search.end_states(map);
+	let predecessors = search.predecessors;
+
+	// walk back from every best way of reaching the end over all minimum-cost predecessors,
+	// turning in place repeats a tile so collapse those into a single step
+	fn walk_back(
+		state: State,
+		predecessors: &AHashMap<State, Vec<State>>,
+		suffix: &mut Vec<(isize, isize)>,
+		paths: &mut AHashSet<Vec<(isize, isize)>>,
+	) {
+		let (pos, _) = state;
+		let pushed = suffix.last() != Some(&pos);
+		if pushed {
+			suffix.push(pos);
 		}
-		if curr_pos == (end_row, end_col) {
-			min_cost = Some(real_cost);
-			found_paths.push(curr_path.clone());
+		match predecessors.get(&state) {
+			Some(prev_states) => prev_states.iter().for_each(|&prev_state| {
+				walk_back(prev_state, predecessors, suffix, paths);
+			}),
+			// only the start has no predecessors
+			None => {
+				paths.insert(suffix.iter().rev().copied().collect());
+			},
 		}
-		// may insert same node multiple times, skip if already seen with lower cost
-		if let Some(&prev_cost) = weights.get(&(curr_pos, curr_dir)) {
-			if real_cost > prev_cost {
-				continue;
-			}
+		if pushed {
+			suffix.pop();
 		}
-		let successors = {
-			// can either step forward or turn each direction
-			let (forward_row, forward_col) = curr_dir.offset_from(curr_pos).unwrap();
-			let forward_move = (map.get(forward_row, forward_col).unwrap() != &MapCell::Wall)
-				.then_some([(((forward_row, forward_col), curr_dir), real_cost + 1)]).into_iter().flatten();
-
-			let turn_moves = curr_dir.perpendicular().into_iter().map(|new_dir|
-				((curr_pos, new_dir), real_cost + 1000));
-			forward_move.chain(turn_moves)
-		};
-		let _ = successors.into_iter().for_each(|((next_pos, next_dir), next_cost)| {
-			match weights.entry((next_pos, next_dir)) {
-				Entry::Vacant(e) => {
-					e.insert(next_cost);
-					let mut path = curr_path.clone();
-					path.insert(next_pos);
-					queue.push(SearchNodePartTwo {
-						est_cost: next_cost + taxicab_distance(next_pos),
-						real_cost: next_cost,
-						path,
-						pos: next_pos,
-						facing: next_dir,
-					});
-				},
-				Entry::Occupied(mut e) => {
-					if next_cost <= *e.get() {
-						e.insert(next_cost);
-						let mut path = curr_path.clone();
-						path.insert(next_pos);
-						queue.push(SearchNodePartTwo {
-							est_cost: next_cost + taxicab_distance(next_pos),
-							real_cost: next_cost,
-							path,
-							pos: next_pos,
-							facing: next_dir,
-						});
-					}
-				}
-			};
-		});
 	}
 
-	let unique_cells = found_paths.into_iter().fold(AHashSet::new(), |unique_cells, path_tiles| {
-		path_tiles.into_iter().fold(unique_cells, |mut unique_cells, pos| {
-			unique_cells.insert(pos);
-			unique_cells
-		})
+	let mut paths = AHashSet::new();
+	end_states.into_iter().for_each(|end_state| {
+		walk_back(end_state, &predecessors, &mut Vec::new(), &mut paths);
 	});
-	Ok(unique_cells.len() as u64)
+
+	paths.into_iter().sorted().collect()
+}
+
+pub fn render_best_paths(input: &str) -> Result<String> {
+	let map = parse_input(input)?;
+	let best_tiles = best_path_tiles(&map);
+
+	let rendered = map.iter_rows().enumerate().map(|(row, cells)| {
+		cells.enumerate().map(|(col, cell)| {
+			if best_tiles.contains(&(row as isize, col as isize)) {
+				'O'
+			} else {
+				char::from(*cell)
+			}
+		}).collect::<String>()
+	}).join("\n");
+
+	Ok(rendered)
 }
 
 #[cfg(test)]
 mod tests {
 	use crate::day16::*;
+	use crate::grid_ext::render;
 
 	const TEST_ONE: &str = "###############
 #.......#....E#
@@ -360,4 +335,129 @@ mod tests {
 		assert_eq!(64, part2(TEST_TWO)?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_render_round_trip() {
+		for test in [TEST_ONE, TEST_TWO] {
+			let map = parse_input(test).unwrap();
+			assert_eq!(test.trim_end(), render(&map, |&cell| char::from(cell)));
+		}
+	}
+
+	#[test]
+	fn test_costs_from_start() -> Result<()> {
+		for test in [TEST_ONE, TEST_TWO] {
+			let costs = costs_from_start(test)?;
+			let start_pos = (test.lines().count() as isize - 2, 1);
+			assert_eq!(Some(&0), costs.get(&(start_pos, Direction::East)));
+			assert_eq!(Some(&1000), costs.get(&(start_pos, Direction::North)));
+
+			let end_pos = (1, test.lines().next().unwrap().len() as isize - 2);
+			let end_cost = Direction::values().into_iter().filter_map(|dir| costs.get(&(end_pos, dir))).min();
+			assert_eq!(Some(part1(test)?), end_cost.copied());
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_part_one_grid() -> Result<()> {
+		for test in [TEST_ONE, TEST_TWO] {
+			assert_eq!(part1(test)?, part1_grid(&parse_input(test)?)?);
+		}
+		assert!(parse_input("#####\n#S?E#\n#####").is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_part_one_breakdown() -> Result<()> {
+		let (steps, turns) = part1_breakdown(TEST_ONE)?;
+		assert_eq!(7036, steps + 1000 * turns);
+		assert_eq!((36, 7), (steps, turns));
+
+		let (steps, turns) = part1_breakdown(TEST_TWO)?;
+		assert_eq!(part1(TEST_TWO)?, steps + 1000 * turns);
+		Ok(())
+	}
+
+	#[test]
+	fn test_part_one_unreachable_end() {
+		assert!(part1("#######\n#S.#.E#\n#######").is_err());
+	}
+
+	#[test]
+	fn test_part_one_facing() -> Result<()> {
+		const NORTH_FIRST: &str = "#####
+#..E#
+#.###
+#S###
+#####";
+		assert_eq!(2004, part1(NORTH_FIRST)?);
+		assert_eq!(2004, part1_facing(NORTH_FIRST, Direction::East)?);
+		// already facing the corridor, so only the one turn at the top is needed
+		assert_eq!(1004, part1_facing(NORTH_FIRST, Direction::North)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_part_two_paths_are_all_optimal() -> Result<()> {
+		fn path_cost(path: &[(isize, isize)]) -> u64 {
+			let (_, cost) = path.iter().tuple_windows().fold((Direction::East, 0), |(facing, cost), (&(from_row, from_col), &to)| {
+				let next_facing = Direction::values().into_iter()
+					.find(|dir| dir.offset_from((from_row, from_col)) == Some(to)).unwrap();
+				let turn_cost = match next_facing {
+					_ if next_facing == facing => 0,
+					_ if next_facing == facing.opposite() => 2000,
+					_ => 1000,
+				};
+				(next_facing, cost + turn_cost + 1)
+			});
+			cost
+		}
+
+		for input in [TEST_ONE, TEST_TWO] {
+			let best_cost = part1(input)?;
+			let paths = part2_paths(input)?;
+			assert!(!paths.is_empty());
+			assert!(paths.iter().all(|path| path_cost(path) == best_cost));
+			let tiles: AHashSet<_> = paths.into_iter().flatten().collect();
+			assert_eq!(part2(input)?, tiles.len() as u64);
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_one_render_best_paths() -> Result<()> {
+		assert_eq!("###############
+#.......#....O#
+#.#.###.#.###O#
+#.....#.#...#O#
+#.###.#####.#O#
+#.#.#.......#O#
+#.#.#####.###O#
+#..OOOOOOOOO#O#
+###O#O#####O#O#
+#OOO#O....#O#O#
+#O#O#O###.#O#O#
+#OOOOO#...#O#O#
+#O###.#.#.#O#O#
+#O..#.....#OOO#
+###############", render_best_paths(TEST_ONE)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_part_two_many_detours() -> Result<()> {
+		// every pillar can be passed above or below at the same cost, so there are 2^18 best paths
+		let walls = format!("##{}##", "#...".repeat(18));
+		let maze = [
+			"#".repeat(walls.len()),
+			walls.clone(),
+			format!("#S{}E#", "..#.".repeat(18)),
+			walls,
+			"#".repeat(76),
+		].join("\n");
+		assert_eq!(72 * 1000 + 73 + 18 * 2, part1(&maze)?);
+		assert_eq!(56 + 2 * 54, part2(&maze)?);
+		Ok(())
+	}
 }
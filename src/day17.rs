@@ -1,5 +1,4 @@
-use std::ops::ControlFlow;
-use ahash::AHashMap;
+use std::fmt::{Display, Formatter};
 use anyhow::*;
 use itertools::Itertools;
 use nom::bytes::complete::tag;
@@ -26,7 +25,9 @@ fn parse_register(input: &str) -> IResult<&str, (Register, usize)> {
 	)(input)
 }
 
-fn parse_registers(input: &str) -> IResult<&str, Vec<(Register, usize)>> {
+type InitialRegisters = Vec<(Register, usize)>;
+
+fn parse_registers(input: &str) -> IResult<&str, InitialRegisters> {
 	separated_list1(line_ending, parse_register)(input)
 }
 
@@ -34,11 +35,17 @@ fn parse_program(input: &str) -> IResult<&str, Vec<usize>> {
 	preceded(tag("Program: "), separated_list1(char(','), parse_usize))(input)
 }
 
-fn parse(input: &str) -> (Vec<(Register, usize)>, Vec<usize>) {
-	let (_, result) = all_consuming(separated_pair(
+fn parse(input: &str) -> Result<(InitialRegisters, Vec<usize>)> {
+	let (_, (registers, program)) = all_consuming(separated_pair(
 		parse_registers, many1(line_ending), parse_program
-	))(input).finish().unwrap();
-	result
+	))(input).finish()
+		.map_err(|err| anyhow!("could not parse input at {:?}: {:?}", err.input.lines().next().unwrap_or_default(), err.code))?;
+
+	// every opcode is followed by its operand
+	if program.len() % 2 != 0 {
+		bail!("program has an opcode without an operand, length {} is odd", program.len());
+	}
+	Ok((registers, program))
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -66,46 +73,35 @@ impl Opcode {
 			_ => None,
 		}
 	}
-	pub fn get_operand(&self, val: usize) -> impl Iterator<Item = Operand> {
+
+	const fn mnemonic(&self) -> &'static str {
 		match self {
-			Opcode::Adv | Opcode::Bdv | Opcode::Cdv =>
-				vec![Operand::Register(Register::A), Operand::parse_combo(val).unwrap()],
-			Opcode::Bxl => vec![Operand::Register(Register::B), Operand::Literal(val)],
-			Opcode::Bst => vec![Operand::parse_combo(val).unwrap()],
-			Opcode::Jnz => vec![Operand::Register(Register::A), Operand::Literal(val)],
-			Opcode::Bxc => vec![Operand::Register(Register::B), Operand::Register(Register::C)],
-			Opcode::Out => vec![Operand::parse_combo(val).unwrap()],
-		}.into_iter()
-	}
-	pub fn execute(&self, data: Vec<usize>) -> Option<(Register, usize)> {
-		let args = data.len();
-		match (self, args) {
-			(Opcode::Adv, 2) => Some((Register::A, data[0] / 2usize.pow(data[1] as u32))),
-			(Opcode::Bxl, 2) => Some((Register::B, data[0] ^ data[1])),
-			(Opcode::Bst, 1) => Some((Register::B, data[0] % 8)),
-			(Opcode::Jnz, 2) => {
-				if data[0] == 0 {
-					None
-				} else {
-					Some((Register::IP, data[1]))
-				}
-			},
-			(Opcode::Bxc, 2) => Some((Register::B, data[0] ^ data[1])),
-			(Opcode::Out, 1) => Some((Register::OUT, data[0] % 8)),
-			(Opcode::Bdv, 2) => Some((Register::B, data[0] / 2usize.pow(data[1] as u32))),
-			(Opcode::Cdv, 2) => Some((Register::C, data[0] / 2usize.pow(data[1] as u32))),
-			_ => None
+			Opcode::Adv => "adv",
+			Opcode::Bxl => "bxl",
+			Opcode::Bst => "bst",
+			Opcode::Jnz => "jnz",
+			Opcode::Bxc => "bxc",
+			Opcode::Out => "out",
+			Opcode::Bdv => "bdv",
+			Opcode::Cdv => "cdv",
 		}
 	}
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-enum Register {
+pub enum Register {
 	A,
 	B,
 	C,
-	IP,
-	OUT
+}
+impl Register {
+	const fn index(&self) -> usize {
+		match self {
+			Register::A => 0,
+			Register::B => 1,
+			Register::C => 2,
+		}
+	}
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -116,7 +112,7 @@ enum Operand {
 impl Operand {
 	pub const fn parse_combo(val: usize) -> Option<Operand> {
 		match val {
-			1..=3 => Some(Operand::Literal(val)),
+			0..=3 => Some(Operand::Literal(val)),
 			4 => Some(Operand::Register(Register::A)),
 			5 => Some(Operand::Register(Register::B)),
 			6 => Some(Operand::Register(Register::C)),
@@ -125,144 +121,192 @@ impl Operand {
 		}
 	}
 }
+impl Display for Operand {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Operand::Literal(val) => write!(f, "{}", val),
+			Operand::Register(register) => write!(f, "{:?}", register),
+		}
+	}
+}
 
-pub fn part1(input: &str) -> Result<String> {
-	let (init_registers, program_memory) = parse(input);
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepResult {
+	Executed,
+	Output(usize),
+	Jumped(usize),
+	Halted,
+}
 
-	let mut register_file = AHashMap::new();
-	register_file.insert(Register::IP, 0);
-	// assume all registers are input with a specific starting value
-	init_registers.into_iter().for_each(|(register, value)| {
-		register_file.insert(register, value);
-	});
-	let mut output = Vec::new();
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Vm {
+	pub registers: [usize; 3],
+	pub program: Vec<usize>,
+	pub ip: usize,
+	pub output: Vec<usize>,
+}
+impl Vm {
+	pub fn new(registers: [usize; 3], program: Vec<usize>) -> Self {
+		Self { registers, program, ..Default::default() }
+	}
 
-	while let Some(&instruction) = program_memory.get(register_file[&Register::IP]) {
-		let opcode = Opcode::try_parse(instruction).unwrap();
-		// description says the computer halts when it tries to read an *opcode* past the end of the program
-		// assume operand always exists in program memory after opcode
-		let operand_request = opcode.get_operand(program_memory[register_file[&Register::IP] + 1]);
-		let operand_values = operand_request.map(|operand_request| {
-			match operand_request {
-				Operand::Literal(val) => val,
-				Operand::Register(reg) => register_file[&reg]
-			}
-		}).collect_vec();
-
-		let mut jumped = false;
-		if let Some((reg_to_modify, new_data)) = opcode.execute(operand_values) {
-			match reg_to_modify {
-				Register::A | Register::B | Register::C => { *register_file.get_mut(&reg_to_modify).unwrap() = new_data; },
-				Register::IP => {
-					*register_file.get_mut(&reg_to_modify).unwrap() = new_data;
-					jumped = true;
-				},
-				Register::OUT => {
-					output.push(new_data);
-				},
-			};
+	pub const fn read_register(&self, register: Register) -> usize {
+		self.registers[register.index()]
+	}
+
+	fn write_register(&mut self, register: Register, value: usize) {
+		self.registers[register.index()] = value;
+	}
+
+	fn read_combo(&self, operand: usize) -> Option<usize> {
+		match Operand::parse_combo(operand)? {
+			Operand::Literal(val) => Some(val),
+			Operand::Register(register) => Some(self.read_register(register)),
 		}
+	}
 
-		if !jumped { *register_file.get_mut(&Register::IP).unwrap() += 2; }
+	fn divide_a(&self, operand: usize) -> Option<usize> {
+		let shift = u32::try_from(self.read_combo(operand)?).ok()?;
+		Some(self.read_register(Register::A).checked_shr(shift).unwrap_or(0))
 	}
 
-	Ok(output.into_iter().join(",").to_string())
-}
+	// executes the instruction at the instruction pointer, returning None if the instruction is invalid
+	pub fn step(&mut self) -> Option<StepResult> {
+		// description says the computer halts when it tries to read an *opcode* past the end of the program
+		let Some(&instruction) = self.program.get(self.ip) else {
+			return Some(StepResult::Halted);
+		};
+		let opcode = Opcode::try_parse(instruction)?;
+		let &operand = self.program.get(self.ip + 1)?;
+
+		let result = match opcode {
+			Opcode::Adv => {
+				self.write_register(Register::A, self.divide_a(operand)?);
+				StepResult::Executed
+			}
+			Opcode::Bxl => {
+				self.write_register(Register::B, self.read_register(Register::B) ^ operand);
+				StepResult::Executed
+			}
+			Opcode::Bst => {
+				self.write_register(Register::B, self.read_combo(operand)? % 8);
+				StepResult::Executed
+			}
+			Opcode::Jnz => {
+				if self.read_register(Register::A) != 0 {
+					self.ip = operand;
+					return Some(StepResult::Jumped(operand));
+				}
+				StepResult::Executed
+			}
+			Opcode::Bxc => {
+				self.write_register(Register::B, self.read_register(Register::B) ^ self.read_register(Register::C));
+				StepResult::Executed
+			}
+			Opcode::Out => {
+				let value = self.read_combo(operand)? % 8;
+				self.output.push(value);
+				StepResult::Output(value)
+			}
+			Opcode::Bdv => {
+				self.write_register(Register::B, self.divide_a(operand)?);
+				StepResult::Executed
+			}
+			Opcode::Cdv => {
+				self.write_register(Register::C, self.divide_a(operand)?);
+				StepResult::Executed
+			}
+		};
 
-// wow I totally misjudged where part 2 would go...
+		self.ip += 2;
+		Some(result)
+	}
 
-#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
-struct ComputerState {
-	reg_a: usize,
-	reg_b: usize,
-	reg_c: usize,
-	inst_ptr: usize,
-	output: Vec<usize>
-}
-impl ComputerState {
-	pub fn read_register(&self, register: Register) -> usize {
-		match register {
-			Register::A => self.reg_a,
-			Register::B => self.reg_b,
-			Register::C => self.reg_c,
-			Register::IP => self.inst_ptr,
-			Register::OUT => unreachable!("OUT register not used in part 2")
+	// steps until the program halts, returning everything output, or fails on an invalid instruction
+	pub fn run(&mut self) -> Result<Vec<usize>> {
+		loop {
+			match self.step() {
+				Some(StepResult::Halted) => break,
+				Some(_) => {},
+				None => bail!("invalid instruction at {}: {:?}", self.ip, &self.program[self.ip..(self.ip + 2).min(self.program.len())]),
+			}
 		}
+
+		Ok(self.output.clone())
 	}
 }
 
-pub fn part2(input: &str) -> Result<usize> {
-	let (init_registers, program_memory) = parse(input);
+fn load(input: &str) -> Result<Vm> {
+	let (init_registers, program_memory) = parse(input)?;
 
-	let mut orig_state = ComputerState::default();
+	let mut vm = Vm::new([0; 3], program_memory);
 	init_registers.into_iter().for_each(|(register, value)| {
-		match register {
-			Register::B => orig_state.reg_b = value,
-			Register::C => orig_state.reg_c = value,
-			_ => {}
-		}
+		vm.write_register(register, value);
 	});
+	Ok(vm)
+}
 
-	let parse_combo = |operand: usize, state: &ComputerState| -> usize {
-		match Operand::parse_combo(operand).unwrap() {
-			Operand::Literal(operand) => operand,
-			Operand::Register(register) => state.read_register(register)
+// one line per instruction, showing combo operands as the register they read
+pub fn disassemble(program: &[usize]) -> Vec<String> {
+	program.chunks(2).map(|instruction| {
+		let (Some(opcode), &[_, operand]) = (Opcode::try_parse(instruction[0]), instruction) else {
+			return format!("??? {}", instruction.iter().join(","));
+		};
+		let mnemonic = opcode.mnemonic();
+		match opcode {
+			Opcode::Adv | Opcode::Bst | Opcode::Out | Opcode::Bdv | Opcode::Cdv => match Operand::parse_combo(operand) {
+				Some(combo) => format!("{} {}", mnemonic, combo),
+				None => format!("{} ??? {}", mnemonic, operand),
+			},
+			Opcode::Bxl | Opcode::Jnz => format!("{} {}", mnemonic, operand),
+			// operand is read but ignored
+			Opcode::Bxc => mnemonic.to_string(),
 		}
-	};
+	}).collect()
+}
 
-	// let mut best_output = 0;
-
-	// println!("goal output: {:?}", program_memory);
-	let found_value = (0..).flat_map(|val| [(val << 33) + 0o132621633, (val << 33) + 0o132621635]).try_for_each(|a_reg_init_val| {
-		let mut state = orig_state.clone();
-		state.reg_a = a_reg_init_val;
-
-		while let Some(&instruction) = program_memory.get(state.inst_ptr) {
-			let opcode = Opcode::try_parse(instruction).unwrap();
-			let operand = program_memory[state.inst_ptr + 1];
-
-			let mut jumped = false;
-			match opcode {
-				Opcode::Adv => { state.reg_a = state.reg_a / 2usize.pow(parse_combo(operand, &state) as u32); }
-				Opcode::Bxl => { state.reg_b = state.reg_b ^ operand; }
-				Opcode::Bst => { state.reg_b = parse_combo(operand, &state) % 8; }
-				Opcode::Jnz => {
-					if state.reg_a != 0 {
-						state.inst_ptr = operand;
-						jumped = true;
-					}
-				}
-				Opcode::Bxc => { state.reg_b = state.reg_b ^ state.reg_c; }
-				Opcode::Out => {
-					let next_output = parse_combo(operand, &state) % 8;
-					// outputting a value cannot be undone or modified
-					// if next value to output does not match what we want to see output next,
-					// we can stop testing for this initial value of the A register
-					if Some(&next_output) != program_memory.get(state.output.len()) {
-						break;
-					}
-					state.output.push(next_output);
-					// if state.output.len() > 11 {
-					// 	best_output = state.output.len();
-					// 	println!("A(0o{:o}) output: {:?}", a_reg_init_val, state.output);
-					// }
-				}
-				Opcode::Bdv => { state.reg_b = state.reg_a / 2usize.pow(parse_combo(operand, &state) as u32); }
-				Opcode::Cdv => { state.reg_c = state.reg_a / 2usize.pow(parse_combo(operand, &state) as u32); }
-			}
+pub struct Day17;
 
-			if !jumped { state.inst_ptr += 2; }
-		}
+pub fn part1(input: &str) -> Result<String> {
+	let mut vm = load(input)?;
+
+	Ok(vm.run()?.into_iter().join(","))
+}
+
+pub fn part2(input: &str) -> Result<usize> {
+	let orig_vm = load(input)?;
 
-		// println!("A({:?}) -> {:?}", a_reg_init_val, state.output);
-		if state.output == program_memory {
-			ControlFlow::Break(a_reg_init_val)
-		} else {
-			ControlFlow::Continue(())
+	// programs consume register A three bits at a time, emitting one output per loop until A is zero,
+	// so the most significant three bits of A decide the final output, the next three bits decide
+	// the output before that, and so on
+	//
+	// build A up three bits at a time, starting from the last output and working back to the first,
+	// keeping only candidates whose output reproduces the required suffix of the program
+	fn find_register_a(a_prefix: usize, suffix_start: usize, orig_vm: &Vm) -> Result<Option<usize>> {
+		let candidates = (0..8).map(|low_bits| (a_prefix << 3) | low_bits)
+			// A starting at zero would never run the loop more than once, skip it
+			.filter(|&candidate| candidate != 0);
+		for candidate in candidates {
+			let mut vm = orig_vm.clone();
+			vm.write_register(Register::A, candidate);
+			if vm.run()? != orig_vm.program[suffix_start..] {
+				continue;
+			}
+			if suffix_start == 0 {
+				return Ok(Some(candidate));
+			}
+			if let Some(reg_a) = find_register_a(candidate, suffix_start - 1, orig_vm)? {
+				return Ok(Some(reg_a));
+			}
 		}
-	});
+		Ok(None)
+	}
 
-	Ok(found_value.break_value().unwrap())
+	let Some(last_ix) = orig_vm.program.len().checked_sub(1) else {
+		bail!("program is empty")
+	};
+	find_register_a(0, last_ix, &orig_vm)?
+		.ok_or_else(|| anyhow!("no initial value of register A reproduces the program"))
 }
 
 #[cfg(test)]
@@ -288,4 +332,128 @@ Register C: 0
 Program: 0,3,5,4,3,0")?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_part_two_reproduces_program() -> Result<()> {
+		// a program shaped like the real puzzle inputs, mixing B and C into each output
+		const INPUT: &str = "Register A: 0
+Register B: 0
+Register C: 0
+
+Program: 2,4,1,1,7,5,1,5,4,0,5,5,0,3,3,0";
+		let reg_a = part2(INPUT)?;
+
+		let mut vm = load(INPUT)?;
+		vm.write_register(Register::A, reg_a);
+		assert_eq!(vm.program.clone(), vm.run()?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_disassemble() {
+		assert_eq!(vec!["adv 1", "out A", "jnz 0"], disassemble(&[0, 1, 5, 4, 3, 0]));
+		assert_eq!(vec!["bst A", "bxl 1", "cdv B", "bxc", "bdv 3"], disassemble(&[2, 4, 1, 1, 7, 5, 4, 0, 6, 3]));
+		assert_eq!(vec!["out ??? 7", "??? 9,1", "??? 3"], disassemble(&[5, 7, 9, 1, 3]));
+	}
+
+	#[test]
+	fn test_odd_length_program() {
+		const ODD: &str = "Register A: 729
+Register B: 0
+Register C: 0
+
+Program: 0,1,5";
+		assert!(part1(ODD).is_err());
+		assert!(part2(ODD).is_err());
+	}
+
+	fn run_single(registers: [usize; 3], instruction: [usize; 2]) -> (Vm, Option<StepResult>) {
+		let mut vm = Vm::new(registers, instruction.to_vec());
+		let result = vm.step();
+		(vm, result)
+	}
+
+	#[test]
+	fn test_adv() {
+		let (vm, result) = run_single([729, 0, 0], [0, 1]);
+		assert_eq!((Some(StepResult::Executed), [364, 0, 0]), (result, vm.registers));
+		let (vm, _) = run_single([729, 3, 0], [0, 5]);
+		assert_eq!([91, 3, 0], vm.registers);
+	}
+
+	#[test]
+	fn test_bxl() {
+		let (vm, result) = run_single([0, 29, 0], [1, 7]);
+		assert_eq!((Some(StepResult::Executed), [0, 26, 0]), (result, vm.registers));
+	}
+
+	#[test]
+	fn test_bst() {
+		let (vm, result) = run_single([0, 0, 9], [2, 6]);
+		assert_eq!((Some(StepResult::Executed), [0, 1, 9]), (result, vm.registers));
+	}
+
+	#[test]
+	fn test_jnz() {
+		let (vm, result) = run_single([0, 0, 0], [3, 4]);
+		assert_eq!((Some(StepResult::Executed), 2), (result, vm.ip));
+		let (vm, result) = run_single([1, 0, 0], [3, 4]);
+		assert_eq!((Some(StepResult::Jumped(4)), 4), (result, vm.ip));
+	}
+
+	#[test]
+	fn test_bxc() {
+		let (vm, result) = run_single([0, 2024, 43690], [4, 0]);
+		assert_eq!((Some(StepResult::Executed), [0, 44354, 43690]), (result, vm.registers));
+	}
+
+	#[test]
+	fn test_out() {
+		let (vm, result) = run_single([13, 0, 0], [5, 4]);
+		assert_eq!((Some(StepResult::Output(5)), vec![5]), (result, vm.output));
+		let (_, result) = run_single([0, 0, 0], [5, 0]);
+		assert_eq!(Some(StepResult::Output(0)), result);
+	}
+
+	#[test]
+	fn test_bdv() {
+		let (vm, result) = run_single([729, 0, 0], [6, 2]);
+		assert_eq!((Some(StepResult::Executed), [729, 182, 0]), (result, vm.registers));
+	}
+
+	#[test]
+	fn test_cdv() {
+		let (vm, result) = run_single([729, 0, 0], [7, 3]);
+		assert_eq!((Some(StepResult::Executed), [729, 0, 91]), (result, vm.registers));
+	}
+
+	#[test]
+	fn test_halt_and_invalid() {
+		let mut vm = Vm::new([0, 0, 0], vec![]);
+		assert_eq!(Some(StepResult::Halted), vm.step());
+		// combo operand 7 is reserved
+		let (_, result) = run_single([0, 0, 0], [5, 7]);
+		assert_eq!(None, result);
+	}
+
+	#[test]
+	fn test_run() -> Result<()> {
+		let mut vm = Vm::new([10, 0, 0], vec![5, 0, 5, 1, 5, 4]);
+		assert_eq!(vec![0, 1, 2], vm.run()?);
+		let mut vm = Vm::new([2024, 0, 0], vec![0, 1, 5, 4, 3, 0]);
+		assert_eq!(vec![4, 2, 5, 6, 7, 7, 7, 7, 3, 1, 0], vm.run()?);
+		assert_eq!(0, vm.read_register(Register::A));
+		Ok(())
+	}
+
+	#[test]
+	fn test_invalid_instruction() {
+		let input = "Register A: 0
+Register B: 0
+Register C: 0
+
+Program: 5,7";
+		assert!(part1(input).is_err());
+		assert!(part2(input).is_err());
+	}
 }
@@ -1,50 +1,16 @@
-use std::cmp::Ordering;
-use std::collections::BinaryHeap;
-use std::collections::hash_map::Entry;
 use std::fmt::{Display, Formatter};
-use std::ops::ControlFlow;
-use ahash::AHashMap;
+use std::str::FromStr;
+use ahash::AHashSet;
 use anyhow::*;
 use grid::Grid;
+use itertools::Itertools;
 use nom::character::complete::{char, digit1, line_ending};
 use nom::combinator::{all_consuming, map, map_res};
 use nom::{Finish, IResult};
 use nom::multi::separated_list1;
 use nom::sequence::separated_pair;
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-enum Direction {
-	North,
-	East,
-	South,
-	West,
-}
-impl Direction {
-	pub const fn get_offset(&self) -> (isize, isize) {
-		match self {
-			Direction::North => (-1, 0),
-			Direction::East => (0, 1),
-			Direction::South => (1, 0),
-			Direction::West => (0, -1),
-		}
-	}
-
-	pub fn offset_from(&self, (row, col): (impl TryInto<isize>, impl TryInto<isize>)) -> Option<(isize, isize)> {
-		let row_isize = row.try_into().ok()?;
-		let col_isize = col.try_into().ok()?;
-		let (row_offset, col_offset) = self.get_offset();
-		Some((row_isize + row_offset, col_isize + col_offset))
-	}
-
-	pub const fn values() -> [Direction; 4] {
-		[
-			Direction::North,
-			Direction::East,
-			Direction::South,
-			Direction::West
-		]
-	}
-}
+use crate::direction::Direction;
+use crate::pathfind::astar;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct BytePos {
@@ -74,6 +40,16 @@ fn parse_pos(input: &str) -> IResult<&str, BytePos> {
 	), |(row, col)| BytePos { row, col })(input)
 }
 
+impl FromStr for BytePos {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self> {
+		let (_, pos) = all_consuming(parse_pos)(s).finish()
+			.map_err(|err| anyhow!("could not parse byte position at {:?}: {:?}", err.input, err.code))?;
+		Ok(pos)
+	}
+}
+
 fn parse(input: &str) -> Vec<BytePos> {
 	let (_, result) = all_consuming(separated_list1(line_ending, parse_pos))(input).finish().unwrap();
 	result
@@ -85,30 +61,16 @@ enum MapCell {
 	Corrupted,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-struct SearchNode {
-	est_cost: u64,
-	real_cost: u64,
+pub struct Day18;
 
-	pos: (isize, isize),
-}
-impl PartialOrd for SearchNode {
-	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-		// inverted, lower weight is better
-		Some(self.cmp(other))
-	}
-}
-impl Ord for SearchNode {
-	fn cmp(&self, other: &Self) -> Ordering {
-		// inverted, lower weight is better
-		other.est_cost.cmp(&self.est_cost)
-	}
+pub fn part1(input: &str) -> Result<u64> {
+	part1_with(input, (71, 71), 1024)
 }
 
-pub fn part1(input: &str) -> Result<u64> {
+pub fn part1_with(input: &str, size: (usize, usize), byte_count: usize) -> Result<u64> {
 	let bytes = parse(input);
 
-	part1_steps_req((71, 71), bytes.into_iter().take(1024))
+	part1_steps_req(size, bytes.into_iter().take(byte_count))
 }
 
 fn part1_steps_req((grid_rows, grid_cols): (usize, usize), bytes: impl IntoIterator<Item = BytePos>) -> Result<u64> {
@@ -120,160 +82,91 @@ fn part1_steps_req((grid_rows, grid_cols): (usize, usize), bytes: impl IntoItera
 		}
 	});
 
-	let (end_row, end_col) = (grid_rows as isize - 1, grid_cols as isize - 1);
-	let taxicab_distance = |(curr_row, curr_col): (isize, isize)| {
-		(curr_row.abs_diff(end_row) + curr_col.abs_diff(end_col)) as u64
+	let end_pos = (grid_rows as isize - 1, grid_cols as isize - 1);
+	let taxicab_distance = |&(curr_row, curr_col): &(isize, isize)| {
+		(curr_row.abs_diff(end_pos.0) + curr_col.abs_diff(end_pos.1)) as u64
 	};
-
-	let mut queue = BinaryHeap::new();
-	let mut weights = AHashMap::new();
-	weights.insert((0, 0), 0);
-	queue.push(SearchNode {
-		est_cost: taxicab_distance((0, 0)),
-		real_cost: 0,
-		pos: (0, 0),
-	});
-
-	while let Some(SearchNode { real_cost, pos: curr_pos, .. }) = queue.pop() {
-		if curr_pos == (end_row, end_col) {
-			return Ok(real_cost);
-		}
-		// may insert same node multiple times, skip if already seen with lower cost
-		if let Some(&prev_cost) = weights.get(&curr_pos) {
-			if real_cost > prev_cost {
-				continue;
+	let grid = &grid;
+	let successors = |&curr_pos: &(isize, isize)| {
+		// can move in any direction that is not occupied by a corrupted byte
+		Direction::values().into_iter().filter_map(move |dir| {
+			let (next_row, next_col) = dir.offset_from(curr_pos).unwrap();
+			match grid.get(next_row, next_col) {
+				Some(&next_cell) if next_cell != MapCell::Corrupted => Some(((next_row, next_col), 1)),
+				_ => None
 			}
-		}
-		let successors = {
-			// can move in any direction that is not occupied by a corrupted byte
-			Direction::values().into_iter().filter_map(|dir| {
-				let (next_row, next_col) = dir.offset_from(curr_pos).unwrap();
-				if let Some(&next_cell) = grid.get(next_row, next_col) {
-					if next_cell != MapCell::Corrupted {
-						Some(((next_row, next_col), real_cost + 1))
-					} else {
-						None
-					}
-				} else {
-					None
-				}
-			})
-		};
-		let _ = successors.into_iter().for_each(|(next_pos, next_cost)| {
-			match weights.entry(next_pos) {
-				Entry::Vacant(e) => {
-					e.insert(next_cost);
-					queue.push(SearchNode {
-						est_cost: next_cost + taxicab_distance(next_pos),
-						real_cost: next_cost,
-						pos: next_pos,
-					});
-				},
-				Entry::Occupied(mut e) => {
-					if next_cost < *e.get() {
-						e.insert(next_cost);
-						queue.push(SearchNode {
-							est_cost: next_cost + taxicab_distance(next_pos),
-							real_cost: next_cost,
-							pos: next_pos,
-						});
-					}
-				}
-			};
-		});
-	}
+		})
+	};
 
-	bail!("No path found?!")
+	let Some((steps, _)) = astar((0, 0), |&pos| pos == end_pos, successors, taxicab_distance) else {
+		bail!("No path found?!")
+	};
+	Ok(steps)
 }
 
-pub fn part2(input: &str) -> Result<BytePos> {
+// every cell that can be reached from the top left corner once the first byte_count bytes have fallen
+pub fn reachable_cells(input: &str, size: (usize, usize), byte_count: usize) -> Result<AHashSet<(isize, isize)>> {
 	let bytes = parse(input);
 
-	part2_blocking_byte((71, 71), bytes)
+	Ok(reachable_from_start(size, bytes.into_iter().take(byte_count)))
 }
 
-fn part2_blocking_byte((grid_rows, grid_cols): (usize, usize), bytes: impl IntoIterator<Item = BytePos>) -> Result<BytePos> {
+fn reachable_from_start((grid_rows, grid_cols): (usize, usize), bytes: impl IntoIterator<Item = BytePos>) -> AHashSet<(isize, isize)> {
 	let mut grid = Grid::init(grid_rows, grid_cols, MapCell::Empty);
-
-	let (end_row, end_col) = (grid_rows as isize - 1, grid_cols as isize - 1);
-	let taxicab_distance = |(curr_row, curr_col): (isize, isize)| {
-		(curr_row.abs_diff(end_row) + curr_col.abs_diff(end_col)) as u64
-	};
-
-	let first_blocking_byte = bytes.into_iter().try_for_each(|next_byte| {
-		let BytePos { row: byte_row, col: byte_col } = next_byte;
+	bytes.into_iter().for_each(|BytePos { row: byte_row, col: byte_col }| {
 		if let Some(grid_cell) = grid.get_mut(byte_row, byte_col) {
 			*grid_cell = MapCell::Corrupted;
 		}
+	});
 
-		let mut queue = BinaryHeap::new();
-		let mut weights = AHashMap::new();
-		weights.insert((0, 0), 0);
-		queue.push(SearchNode {
-			est_cost: taxicab_distance((0, 0)),
-			real_cost: 0,
-			pos: (0, 0),
+	let mut reachable = AHashSet::new();
+	if grid.get(0, 0) != Some(&MapCell::Empty) {
+		return reachable;
+	}
+	reachable.insert((0, 0));
+	let mut to_visit = vec![(0, 0)];
+	while let Some(curr_pos) = to_visit.pop() {
+		Direction::values().into_iter().flat_map(|dir| dir.offset_from(curr_pos)).for_each(|(next_row, next_col)| {
+			if grid.get(next_row, next_col) == Some(&MapCell::Empty) && reachable.insert((next_row, next_col)) {
+				to_visit.push((next_row, next_col));
+			}
 		});
+	}
 
-		let mut path_found = false;
-		while let Some(SearchNode { real_cost, pos: curr_pos, .. }) = queue.pop() {
-			if curr_pos == (end_row, end_col) {
-				path_found = true;
-				break;
-			}
-			// may insert same node multiple times, skip if already seen with lower cost
-			if let Some(&prev_cost) = weights.get(&curr_pos) {
-				if real_cost > prev_cost {
-					continue;
-				}
-			}
-			let successors = {
-				// can move in any direction that is not occupied by a corrupted byte
-				Direction::values().into_iter().filter_map(|dir| {
-					let (next_row, next_col) = dir.offset_from(curr_pos).unwrap();
-					if let Some(&next_cell) = grid.get(next_row, next_col) {
-						if next_cell != MapCell::Corrupted {
-							Some(((next_row, next_col), real_cost + 1))
-						} else {
-							None
-						}
-					} else {
-						None
-					}
-				})
-			};
-			let _ = successors.into_iter().for_each(|(next_pos, next_cost)| {
-				match weights.entry(next_pos) {
-					Entry::Vacant(e) => {
-						e.insert(next_cost);
-						queue.push(SearchNode {
-							est_cost: next_cost + taxicab_distance(next_pos),
-							real_cost: next_cost,
-							pos: next_pos,
-						});
-					},
-					Entry::Occupied(mut e) => {
-						if next_cost < *e.get() {
-							e.insert(next_cost);
-							queue.push(SearchNode {
-								est_cost: next_cost + taxicab_distance(next_pos),
-								real_cost: next_cost,
-								pos: next_pos,
-							});
-						}
-					}
-				};
-			});
-		}
+	reachable
+}
+
+pub fn part2(input: &str) -> Result<BytePos> {
+	part2_with(input, (71, 71))
+}
+
+pub fn part2_with(input: &str, size: (usize, usize)) -> Result<BytePos> {
+	let bytes = parse(input);
 
-		if path_found {
-			ControlFlow::Continue(())
+	part2_blocking_byte(size, bytes)
+}
+
+fn part2_blocking_byte(grid_size: (usize, usize), bytes: impl IntoIterator<Item = BytePos>) -> Result<BytePos> {
+	let bytes = bytes.into_iter().collect_vec();
+	let path_exists = |byte_count: usize| part1_steps_req(grid_size, bytes.iter().take(byte_count).copied()).is_ok();
+
+	// once the path is blocked, dropping more bytes can never unblock it,
+	// so binary search for the shortest prefix of bytes that blocks the path
+	let (mut blocked_low, mut blocked_high) = (0, bytes.len());
+	if path_exists(blocked_high) {
+		bail!("No byte blocks the path?!")
+	}
+	while blocked_low < blocked_high {
+		let mid = (blocked_low + blocked_high) / 2;
+		if path_exists(mid) {
+			blocked_low = mid + 1;
 		} else {
-			ControlFlow::Break(next_byte)
+			blocked_high = mid;
 		}
-	});
+	}
 
-	Ok(first_blocking_byte.break_value().unwrap())
+	// the last byte of the shortest blocking prefix is the one that blocked the path
+	Ok(bytes[blocked_low - 1])
 }
 
 #[cfg(test)]
@@ -312,9 +205,56 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_reachable_cells() -> Result<()> {
+		let reachable = reachable_cells(TEST, (7, 7), 12)?;
+		assert!(reachable.contains(&(6, 6)));
+		// 37 cells are left uncorrupted, but (1, 6) is walled off in its corner
+		assert_eq!(36, reachable.len());
+		assert!(!reachable.contains(&(1, 6)));
+
+		// once the blocking byte falls the end is cut off
+		assert!(!reachable_cells(TEST, (7, 7), 21)?.contains(&(6, 6)));
+		Ok(())
+	}
+
 	#[test]
 	fn test_part_two() -> Result<()> {
 		assert_eq!(BytePos::from((6, 1)), part2_blocking_byte((7, 7), parse(TEST).into_iter())?);
 		Ok(())
 	}
+
+	fn linear_blocking_byte(grid_size: (usize, usize), bytes: Vec<BytePos>) -> Option<BytePos> {
+		(1..=bytes.len())
+			.find(|&byte_count| part1_steps_req(grid_size, bytes.iter().take(byte_count).copied()).is_err())
+			.map(|byte_count| bytes[byte_count - 1])
+	}
+
+	#[test]
+	fn test_part_two_matches_linear_scan() -> Result<()> {
+		assert_eq!(linear_blocking_byte((7, 7), parse(TEST)), Some(part2_blocking_byte((7, 7), parse(TEST))?));
+
+		// the full input isn't committed, only compare against it when it's available
+		if let Result::Ok(full_input) = std::fs::read_to_string("input/18.txt") {
+			assert_eq!(linear_blocking_byte((71, 71), parse(&full_input)), Some(part2(&full_input)?));
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_byte_pos_from_str() -> Result<()> {
+		assert_eq!("6,1", "6,1".parse::<BytePos>()?.to_string());
+		assert_eq!(BytePos::from((6, 1)), "6,1".parse()?);
+		assert!("6".parse::<BytePos>().is_err());
+		assert!("6,1,2".parse::<BytePos>().is_err());
+		assert!("-6,1".parse::<BytePos>().is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_public_wrappers() -> Result<()> {
+		assert_eq!(22, part1_with(TEST, (7, 7), 12)?);
+		assert_eq!(BytePos::from((6, 1)), part2_with(TEST, (7, 7))?);
+		Ok(())
+	}
 }
@@ -10,7 +10,7 @@ use nom::multi::{many1, separated_list1};
 use nom::sequence::separated_pair;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-enum Stripe {
+pub enum Stripe {
 	White,
 	Blue,
 	Black,
@@ -41,8 +41,8 @@ fn parse_stripe(input: &str) -> IResult<&str, Stripe> {
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-struct Towel {
-	stripes: Vec<Stripe>
+pub struct Towel {
+	pub stripes: Vec<Stripe>
 }
 impl Display for Towel {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -56,6 +56,13 @@ fn parse_towel(input: &str) -> IResult<&str, Towel> {
 	map(many1(parse_stripe), |stripes| Towel { stripes })(input)
 }
 
+// a single towel or design, written the same way as in the puzzle input
+pub fn parse_design(s: &str) -> Result<Towel> {
+	let (_, towel) = all_consuming(parse_towel)(s).finish()
+		.map_err(|err| anyhow!("could not parse design at {:?}: {:?}", err.input, err.code))?;
+	Ok(towel)
+}
+
 fn parse_available_patterns(input: &str) -> IResult<&str, Vec<Towel>> {
 	separated_list1(tag(", "), parse_towel)(input)
 }
@@ -69,9 +76,40 @@ fn parse(input: &str) -> (Vec<Towel>, Vec<Towel>) {
 	result
 }
 
+pub struct Day19;
+
 pub fn part1(input: &str) -> Result<u64> {
 	let (available_patterns, goal_patterns) = parse(input);
 
+	let found_goals = goal_patterns.iter().filter(|goal_towel| is_possible(goal_towel, &available_patterns)).count();
+
+	Ok(found_goals as u64)
+}
+
+// walk forward through the goal, marking every position a towel can end at
+// starting from a position that was already reachable
+fn is_possible(goal_towel: &Towel, available_patterns: &[Towel]) -> bool {
+	let goal_len = goal_towel.stripes.len();
+	let mut reachable = vec![false; goal_len + 1];
+	reachable[0] = true;
+
+	for start_ix in 0..goal_len {
+		if !reachable[start_ix] {
+			continue;
+		}
+		let remaining = &goal_towel.stripes[start_ix..];
+		available_patterns.iter()
+			.filter(|towel| remaining.starts_with(&towel.stripes))
+			.for_each(|towel| reachable[start_ix + towel.stripes.len()] = true);
+	}
+
+	reachable[goal_len]
+}
+
+// original implementation, splitting the goal at every index and checking both halves
+pub fn part1_split(input: &str) -> Result<u64> {
+	let (available_patterns, goal_patterns) = parse(input);
+
 	let mut solved_patterns = AHashMap::from_iter(available_patterns.into_iter().map(|towel| (towel, true)));
 
 	let mut calculate_if_possible = |towel: Towel| -> bool {
@@ -167,6 +205,22 @@ bbrgwb";
 		Ok(())
 	}
 
+	#[test]
+	fn test_part_one_split() -> Result<()> {
+		assert_eq!(6, part1_split(TEST)?);
+		assert_eq!(part1_split(TEST)?, part1(TEST)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_parse_design() -> Result<()> {
+		assert_eq!("brwrr", parse_design("brwrr")?.to_string());
+		assert_eq!(vec![Stripe::Blue, Stripe::Green], parse_design("ug")?.stripes);
+		assert!(parse_design("brxrr").is_err());
+		assert!(parse_design("").is_err());
+		Ok(())
+	}
+
 	#[test]
 	fn test_part_two() -> Result<()> {
 		assert_eq!(16, part2(TEST)?);
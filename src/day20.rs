@@ -1,3 +1,4 @@
+use std::collections::VecDeque;
 use anyhow::*;
 use grid::Grid;
 use nom::character::complete::{line_ending, one_of};
@@ -5,6 +6,8 @@ use nom::combinator::{all_consuming, map_res, opt};
 use nom::{Finish, IResult};
 use nom::multi::many1;
 use nom::sequence::terminated;
+use crate::direction::Direction;
+use crate::grid_ext::SignedGridExt;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum MapCell {
@@ -14,6 +17,17 @@ enum MapCell {
 	Empty,
 }
 
+impl From<MapCell> for char {
+	fn from(value: MapCell) -> Self {
+		match value {
+			MapCell::Start => 'S',
+			MapCell::End => 'E',
+			MapCell::Wall => '#',
+			MapCell::Empty => '.',
+		}
+	}
+}
+
 fn parse_cell(input: &str) -> IResult<&str, MapCell> {
 	map_res(one_of("SE#."), |chr| {match chr {
 		'S' => Ok(MapCell::Start),
@@ -36,48 +50,7 @@ fn parse(input: &str) -> Vec<Vec<MapCell>> {
 	result
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-enum Direction {
-	North,
-	East,
-	South,
-	West,
-}
-impl Direction {
-	pub const fn get_offset(&self) -> (isize, isize) {
-		match self {
-			Direction::North => (-1, 0),
-			Direction::East => (0, 1),
-			Direction::South => (1, 0),
-			Direction::West => (0, -1),
-		}
-	}
-
-	pub fn offset_from(&self, (row, col): (impl TryInto<isize>, impl TryInto<isize>)) -> Option<(isize, isize)> {
-		let row_isize = row.try_into().ok()?;
-		let col_isize = col.try_into().ok()?;
-		let (row_offset, col_offset) = self.get_offset();
-		Some((row_isize + row_offset, col_isize + col_offset))
-	}
-
-	pub const fn values() -> [Direction; 4] {
-		[
-			Direction::North,
-			Direction::East,
-			Direction::South,
-			Direction::West
-		]
-	}
-
-	pub const fn opposite(&self) -> Direction {
-		match self {
-			Direction::North => Direction::South,
-			Direction::East => Direction::West,
-			Direction::South => Direction::North,
-			Direction::West => Direction::East,
-		}
-	}
-}
+pub struct Day20;
 
 pub fn part1(input: &str) -> Result<usize> {
 	part1_with_savings(input, 100)
@@ -85,144 +58,110 @@ pub fn part1(input: &str) -> Result<usize> {
 
 pub fn part1_with_savings(input: &str, min_savings: usize) -> Result<usize> {
 	let map: Grid<_> = parse(input).into();
-	let start_pos = map.indexed_iter().find_map(|((row, col), &cell)| {
-		if cell == MapCell::Start {
-			Some((row as isize, col as isize))
-		} else {
-			None
-		}
-	}).unwrap();
-
-	// map is supposed to represent a racecourse
-	// assume map only has one path from start to finish, with no branches/dead ends
-	// starting from the start and simply not proceeding backwards should find the path from start to end
-	let mut course = Vec::from([start_pos]);
-	let mut backwards = None;
-	let mut curr_pos = start_pos;
-	while map.get(curr_pos.0, curr_pos.1).unwrap() != &MapCell::End {
-		let mut to_check = Direction::values().into_iter().filter_map(|dir| {
-			if let Some(backwards) = backwards {
-				if backwards != dir {
-					Some((dir, dir.offset_from(curr_pos).unwrap()))
-				} else {
-					None
-				}
-			} else {
-				Some((dir, dir.offset_from(curr_pos).unwrap()))
-			}
-		});
-		let (next_dir, next_pos) = to_check.find(|&(_, (row_to_check, col_to_check))|
-			map.get(row_to_check, col_to_check).unwrap() != &MapCell::Wall
-		).unwrap();
-
-		course.push(next_pos);
-		backwards = Some(next_dir.opposite());
-		curr_pos = next_pos;
-	}
-
-	let mut time_map = Grid::init(map.rows(), map.cols(), None);
-	course.iter().enumerate().for_each(|(time, &(row, col))| {
-		*time_map.get_mut(row, col).unwrap() = Some(time);
-	});
-
-	let all_skip_lens = course.iter().flat_map(|&(row, col)| {
-		let start_time = time_map.get(row, col).cloned().flatten().unwrap();
+	let RaceTimes { from_start, to_end, best_time } = race_times(&map)?;
+	let to_end = &to_end;
 
+	let all_skip_lens = from_start.indexed_iter().filter_map(|((row, col), &start_time)| {
+		start_time.map(|start_time| ((row as isize, col as isize), start_time))
+	}).flat_map(|((row, col), start_time)| {
 		// for each point along the course, check two cells in each direction to see if there is
-		// a later point in the race we can reach
+		// a point in the race we can reach that is closer to the end
 		let possible_skips_to = Direction::values().into_iter().filter_map(move |dir|
 			dir.offset_from((row, col)).and_then(|(once_offset_row, once_offset_col)|
 				dir.offset_from((once_offset_row, once_offset_col)))
 		).filter_map(|(offset_row, offset_col)|
-			time_map.get(offset_row, offset_col).cloned().flatten()
+			to_end.get_signed(offset_row, offset_col).copied().flatten()
 		);
 
 		// check that we do save time (we can be trying to skip backwards), but assuming we do,
-		// the time saved by the skip is the difference in the start and end points,
-		// minus the 2 picoseconds spent traversing the skip
-		possible_skips_to.filter_map(move |skip_end_time| {
-			if skip_end_time > start_time + 2 {
-				Some(skip_end_time - start_time - 2)
-			} else {
-				None
-			}
-		})
+		// the time saved by the skip is the best time, minus the time to reach the skip start,
+		// the 2 picoseconds spent traversing the skip, and the time left after the skip
+		possible_skips_to.filter_map(move |remaining_time| best_time.checked_sub(start_time + 2 + remaining_time))
 	});
 
-	Ok(all_skip_lens.filter(|&skip_savings| skip_savings >= min_savings).count())
+	Ok(all_skip_lens.filter(|&skip_savings| skip_savings > 0 && skip_savings >= min_savings).count())
 }
 
 pub fn part2(input: &str) -> Result<usize> {
-	part2_with_cheat_len_and_savings_min(input, 20, 100)
+	part2_with_savings(input, 20, 100)
 }
 
-pub fn part2_with_cheat_len_and_savings_min(input: &str, max_cheat: usize, min_savings: usize) -> Result<usize> {
+pub fn part2_with_savings(input: &str, cheat_len: usize, min_savings: usize) -> Result<usize> {
 	let map: Grid<_> = parse(input).into();
-	let start_pos = map.indexed_iter().find_map(|((row, col), &cell)| {
-		if cell == MapCell::Start {
-			Some((row as isize, col as isize))
-		} else {
-			None
-		}
-	}).unwrap();
-
-	// map is supposed to represent a racecourse
-	// assume map only has one path from start to finish, with no branches/dead ends
-	// starting from the start and simply not proceeding backwards should find the path from start to end
-	let mut course = Vec::from([start_pos]);
-	let mut backwards = None;
-	let mut curr_pos = start_pos;
-	while map.get(curr_pos.0, curr_pos.1).unwrap() != &MapCell::End {
-		let mut to_check = Direction::values().into_iter().filter_map(|dir| {
-			if let Some(backwards) = backwards {
-				if backwards != dir {
-					Some((dir, dir.offset_from(curr_pos).unwrap()))
-				} else {
-					None
-				}
-			} else {
-				Some((dir, dir.offset_from(curr_pos).unwrap()))
-			}
-		});
-		let (next_dir, next_pos) = to_check.find(|&(_, (row_to_check, col_to_check))|
-			map.get(row_to_check, col_to_check).unwrap() != &MapCell::Wall
-		).unwrap();
+	let RaceTimes { from_start, to_end, best_time } = race_times(&map)?;
+	let to_end = &to_end;
+
+	let cheat_len_isize = cheat_len as isize;
+	let cheat_offsets = (-cheat_len_isize..=cheat_len_isize).flat_map(|row_offset| {
+		let col_len = cheat_len_isize - row_offset.abs();
+		(-col_len..=col_len).map(move |col_offset| (row_offset, col_offset))
+	}).collect::<Vec<_>>();
+	let cheat_offsets = &cheat_offsets;
+
+	let all_skip_lens = from_start.indexed_iter().filter_map(|((row, col), &start_time)| {
+		start_time.map(|start_time| ((row as isize, col as isize), start_time))
+	}).flat_map(|((row, col), start_time)| {
+		// a cheat is defined by its start and end points
+		// the specific path taken during the cheat is not important,
+		// so long as we can reach the end point in the time allowed
+		cheat_offsets.iter().filter_map(move |&(row_offset, col_offset)| {
+			let remaining_time = to_end.get_signed(row + row_offset, col + col_offset).copied().flatten()?;
+			let cheat_distance = row_offset.unsigned_abs() + col_offset.unsigned_abs();
 
-		course.push(next_pos);
-		backwards = Some(next_dir.opposite());
-		curr_pos = next_pos;
-	}
+			// can we cheat to the destination? if so, how much time do we save?
+			best_time.checked_sub(start_time + cheat_distance + remaining_time)
+		})
+	});
 
-	let mut time_map = Grid::init(map.rows(), map.cols(), None);
-	course.iter().enumerate().for_each(|(time, &(row, col))| {
-		*time_map.get_mut(row, col).unwrap() = Some(time);
+	Ok(all_skip_lens.filter(|&skip_savings: &usize| skip_savings > 0 && skip_savings >= min_savings).count())
+}
+
+struct RaceTimes {
+	from_start: Grid<Option<usize>>,
+	to_end: Grid<Option<usize>>,
+	best_time: usize,
+}
+
+// the course may branch or have dead ends, so find the quickest time from the start to every point on it,
+// and from every point on it to the end
+fn race_times(map: &Grid<MapCell>) -> Result<RaceTimes> {
+	let find_cell = |target| map.indexed_iter().find_map(|((row, col), &cell)| {
+		(cell == target).then_some((row as isize, col as isize))
 	});
+	let Some(start_pos) = find_cell(MapCell::Start) else {
+		bail!("no start found");
+	};
+	let Some(end_pos) = find_cell(MapCell::End) else {
+		bail!("no end found");
+	};
 
-	let taxicab_distance = |(curr_row, curr_col): (isize, isize), (target_row, target_col): (isize, isize)| {
-		curr_row.abs_diff(target_row) + curr_col.abs_diff(target_col)
+	let from_start = bfs_times(map, start_pos);
+	let to_end = bfs_times(map, end_pos);
+	let Some(best_time) = from_start.get_signed(end_pos.0, end_pos.1).copied().flatten() else {
+		bail!("course does not reach End");
 	};
 
-	let all_skip_lens = course.iter().enumerate().flat_map(|(start_time, &(row, col))| {
-		// a cheat is defined by its start and end points
-		// the specific path taken during the cheat is not important,
-		// so long as we can reach the end point in the time allowed
-		let good_destinations = course.iter().enumerate().rev().take_while(move |&(end_time, _)|
-			// skip locations that cannot save enough time anyway
-			end_time - start_time >= min_savings
-		);
+	Ok(RaceTimes { from_start, to_end, best_time })
+}
 
-		// can we cheat to the destination? if so, how much time do we save?
-		good_destinations.filter_map(move |(end_time, &(end_row, end_col))| {
-			let cheat_distance = taxicab_distance((row, col), (end_row, end_col));
-			if cheat_distance <= max_cheat && start_time + cheat_distance <= end_time {
-				Some(end_time - start_time - cheat_distance)
-			} else {
-				None
+fn bfs_times(map: &Grid<MapCell>, from: (isize, isize)) -> Grid<Option<usize>> {
+	let mut time_map = Grid::init(map.rows(), map.cols(), None);
+	*time_map.get_signed_mut(from.0, from.1).unwrap() = Some(0);
+
+	let mut queue = VecDeque::from([(from, 0)]);
+	while let Some((curr_pos, time)) = queue.pop_front() {
+		Direction::values().into_iter().flat_map(|dir| dir.offset_from(curr_pos)).for_each(|(next_row, next_col)| {
+			let on_track = map.get_signed(next_row, next_col).is_some_and(|&cell| cell != MapCell::Wall);
+			if let Some(next_time @ None) = time_map.get_signed_mut(next_row, next_col) {
+				if on_track {
+					*next_time = Some(time + 1);
+					queue.push_back(((next_row, next_col), time + 1));
+				}
 			}
-		})
-	});
+		});
+	}
 
-	Ok(all_skip_lens.filter(|&skip_savings: &usize| skip_savings >= min_savings).count())
+	time_map
 }
 
 #[cfg(test)]
@@ -253,7 +192,47 @@ mod tests {
 
 	#[test]
 	fn test_part_two() -> Result<()> {
-		assert_eq!(7, part2_with_cheat_len_and_savings_min(TEST, 20, 74)?);
+		assert_eq!(7, part2_with_savings(TEST, 20, 74)?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_part_two_fifty_or_more() -> Result<()> {
+		// sum of all the cheats listed in the puzzle description that save at least 50 picoseconds
+		assert_eq!(32 + 31 + 29 + 39 + 25 + 23 + 20 + 19 + 12 + 14 + 12 + 22 + 4 + 3, part2_with_savings(TEST, 20, 50)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_part_two_two_step_cheat() -> Result<()> {
+		// a cheat of length 2 is exactly the part 1 rule
+		assert_eq!(part1_with_savings(TEST, 20)?, part2_with_savings(TEST, 2, 20)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_branching_course() -> Result<()> {
+		// heading north from the start leads into a dead end
+		const BRANCHING: &str = "#######
+#.#...#
+#.#.#.#
+#S..#E#
+#######";
+		// (3, 3) to the end saves 4, (2, 3) to (2, 5) saves 2
+		assert_eq!(2, part1_with_savings(BRANCHING, 1)?);
+		assert_eq!(1, part1_with_savings(BRANCHING, 3)?);
+		assert_eq!(2, part2_with_savings(BRANCHING, 2, 1)?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_unreachable_end() {
+		const WALLED_OFF: &str = "#######
+#S.#..#
+#..#.E#
+#######";
+		let result = part1_with_savings(WALLED_OFF, 1);
+		assert_eq!("course does not reach End", result.unwrap_err().to_string());
+		assert!(part2_with_savings(WALLED_OFF, 20, 1).is_err());
+	}
 }
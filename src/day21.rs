@@ -9,9 +9,10 @@ use nom::character::complete::{line_ending, one_of};
 use nom::combinator::{all_consuming, map_res};
 use nom::{Finish, IResult};
 use nom::multi::{many1, separated_list1};
+use crate::direction::Direction;
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
-enum NumericKeypad {
+pub enum NumericKeypad {
 	Zero,
 	One,
 	Two,
@@ -53,22 +54,24 @@ impl NumericKeypad {
 		}
 	}
 
-	pub fn move_to(&self, target: &NumericKeypad) -> Vec<Vec<MovementDirection>> {
+	// every shortest sequence of moves from this button to the target that never crosses the gap,
+	// e.g. Activate to One is either North, West, West or West, North, West but never West, West, North
+	pub fn move_to(&self, target: &NumericKeypad) -> Vec<Vec<Direction>> {
 		let (start_row, start_col) = self.get_pos();
 		let (end_row, end_col) = target.get_pos();
 
 		let row_delta = start_row.abs_diff(end_row);
 		let row_steps = if start_row < end_row {
-			repeat_n(MovementDirection::Down, row_delta)
+			repeat_n(Direction::South, row_delta)
 		} else {
-			repeat_n(MovementDirection::Up, row_delta)
+			repeat_n(Direction::North, row_delta)
 		};
 
 		let col_delta = start_col.abs_diff(end_col);
 		let col_steps = if start_col < end_col {
-			repeat_n(MovementDirection::Right, col_delta)
+			repeat_n(Direction::East, col_delta)
 		} else {
-			repeat_n(MovementDirection::Left, col_delta)
+			repeat_n(Direction::West, col_delta)
 		};
 
 		let move_permutation = row_steps.chain(col_steps).collect_vec();
@@ -115,7 +118,7 @@ impl Default for NumericKeypad {
 }
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
-enum DirectionalKeypad {
+pub enum DirectionalKeypad {
 	Up,
 	Left,
 	Down,
@@ -141,22 +144,24 @@ impl DirectionalKeypad {
 		}
 	}
 
-	pub fn move_to(&self, target: &DirectionalKeypad) -> Vec<Vec<MovementDirection>> {
+	// every shortest sequence of moves from this button to the target that never crosses the gap,
+	// e.g. Left to Up is only ever East, North
+	pub fn move_to(&self, target: &DirectionalKeypad) -> Vec<Vec<Direction>> {
 		let (start_row, start_col) = self.get_pos();
 		let (end_row, end_col) = target.get_pos();
 
 		let row_delta = start_row.abs_diff(end_row);
 		let row_steps = if start_row < end_row {
-			repeat_n(MovementDirection::Down, row_delta)
+			repeat_n(Direction::South, row_delta)
 		} else {
-			repeat_n(MovementDirection::Up, row_delta)
+			repeat_n(Direction::North, row_delta)
 		};
 
 		let col_delta = start_col.abs_diff(end_col);
 		let col_steps = if start_col < end_col {
-			repeat_n(MovementDirection::Right, col_delta)
+			repeat_n(Direction::East, col_delta)
 		} else {
-			repeat_n(MovementDirection::Left, col_delta)
+			repeat_n(Direction::West, col_delta)
 		};
 
 		let move_permutation = row_steps.chain(col_steps).collect_vec();
@@ -178,13 +183,13 @@ impl DirectionalKeypad {
 		}).collect_vec()
 	}
 }
-impl From<MovementDirection> for DirectionalKeypad {
-	fn from(value: MovementDirection) -> Self {
+impl From<Direction> for DirectionalKeypad {
+	fn from(value: Direction) -> Self {
 		match value {
-			MovementDirection::Up => DirectionalKeypad::Up,
-			MovementDirection::Right => DirectionalKeypad::Right,
-			MovementDirection::Down => DirectionalKeypad::Down,
-			MovementDirection::Left => DirectionalKeypad::Left,
+			Direction::North => DirectionalKeypad::Up,
+			Direction::East => DirectionalKeypad::Right,
+			Direction::South => DirectionalKeypad::Down,
+			Direction::West => DirectionalKeypad::Left,
 		}
 	}
 }
@@ -220,69 +225,63 @@ fn parse(input: &str) -> Vec<Vec<NumericKeypad>> {
 	result
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-enum MovementDirection {
-	Up,
-	Right,
-	Down,
-	Left,
+pub struct Day21;
+
+pub fn part1(input: &str) -> Result<u64> {
+	solve(input, 3)
 }
-impl MovementDirection {
-	pub const fn get_offset(&self) -> (isize, isize) {
-		match self {
-			MovementDirection::Up => (-1, 0),
-			MovementDirection::Right => (0, 1),
-			MovementDirection::Down => (1, 0),
-			MovementDirection::Left => (0, -1),
-		}
-	}
 
-	pub fn offset_from(&self, (row, col): (impl TryInto<isize>, impl TryInto<isize>)) -> Option<(isize, isize)> {
-		let row_isize = row.try_into().ok()?;
-		let col_isize = col.try_into().ok()?;
-		let (row_offset, col_offset) = self.get_offset();
-		Some((row_isize + row_offset, col_isize + col_offset))
+type CostCache = AHashMap<(DirectionalKeypad, DirectionalKeypad, u64), u64>;
+
+// number of human presses needed for the robot `depth` levels down to move from one directional button to another and press it
+fn directional_cost(prev_button: &DirectionalKeypad, next_button: &DirectionalKeypad, depth: u64, cache: &mut CostCache) -> u64 {
+	if let Some(&val) = cache.get(&(*prev_button, *next_button, depth)) {
+		return val;
 	}
+
+	let cost = if depth == 0 {
+		// cost of the button is just pressing the button
+		1
+	} else {
+		let possible_movesets = prev_button.move_to(next_button);
+		// find the moveset with the lowest cost
+		possible_movesets.iter().map(|moveset| moveset_cost(moveset, depth - 1, cache)).min().unwrap()
+	};
+	cache.insert((*prev_button, *next_button, depth), cost);
+
+	cost
 }
 
-pub fn part1(input: &str) -> Result<u64> {
-	solve(input, 3)
+// the controlling robot always starts from and ends on Activate, so a moveset is pressed as the moves followed by Activate
+fn moveset_buttons(moveset: &[Direction]) -> impl Iterator<Item = DirectionalKeypad> + '_ {
+	moveset.iter().copied().map(DirectionalKeypad::from).chain(once(DirectionalKeypad::Activate))
 }
 
-fn solve(input: &str, robot_chain_len: u64) -> Result<u64> {
-	let input_codes = parse(input);
+fn moveset_cost(moveset: &[Direction], depth: u64, cache: &mut CostCache) -> u64 {
+	let (cost, _) = moveset_buttons(moveset)
+		.fold((0, DirectionalKeypad::default()), |(sum, prev_btn), next_btn| {
+			// the cost of a set of moves is the cost of each individual move, summed together
+			(sum + directional_cost(&prev_btn, &next_btn, depth, cache), next_btn)
+		});
+	cost
+}
 
-	let mut cost_cache: AHashMap<(DirectionalKeypad, DirectionalKeypad, u64), u64> = AHashMap::new();
+fn cheapest_moveset(possible_movesets: Vec<Vec<Direction>>, depth: u64, cache: &mut CostCache) -> (Vec<Direction>, u64) {
+	possible_movesets.into_iter()
+		.map(|moveset| {
+			let cost = moveset_cost(&moveset, depth, cache);
+			(moveset, cost)
+		})
+		.min_by_key(|&(_, cost)| cost).unwrap()
+}
 
-	let mut get_cost = |prev_button: &DirectionalKeypad, next_button: &DirectionalKeypad, depth: u64| -> u64 {
-		fn rec(prev_button: &DirectionalKeypad, next_button: &DirectionalKeypad, depth: u64, cache: &mut AHashMap<(DirectionalKeypad, DirectionalKeypad, u64), u64>) -> u64 {
-			if let Some(&val) = cache.get(&(*prev_button, *next_button, depth)) {
-				return val;
-			}
+pub fn solve(input: &str, robot_chain_len: u64) -> Result<u64> {
+	if robot_chain_len == 0 {
+		bail!("need at least one robot to press the numeric keypad");
+	}
+	let input_codes = parse(input);
 
-			let cost = if depth == 0 {
-				// cost of the button is just pressing the button
-				1
-			} else {
-				let possible_movesets = prev_button.move_to(next_button);
-				let moveset_cost = possible_movesets.into_iter().map(|moveset| {
-					let (cost, _) = moveset.into_iter().map(DirectionalKeypad::from).chain(once(DirectionalKeypad::Activate))
-						.fold((0, DirectionalKeypad::default()), |(sum, prev_btn), next_btn| {
-							// the cost of a set of moves is the cost of each individual move, summed together
-							(sum + rec(&prev_btn, &next_btn, depth - 1, cache), next_btn)
-						});
-					cost
-				});
-				// find the moveset with the lowest cost
-				let min_cost = moveset_cost.min().unwrap();
-				min_cost
-			};
-			cache.insert((*prev_button, *next_button, depth), cost);
-
-			cost
-		}
-		rec(prev_button, next_button, depth, &mut cost_cache)
-	};
+	let mut cost_cache = CostCache::new();
 
 	let complexities = input_codes.into_iter().map(|input_code| {
 		// each input code is a sequence of numeric keypad presses we need to input, but indirectly
@@ -300,24 +299,59 @@ fn solve(input: &str, robot_chain_len: u64) -> Result<u64> {
 			}
 		});
 
-		let (input_min_cost, _) = input_code.into_iter().fold((0, NumericKeypad::default()), |(cost, prev_button), next_button| {
-			let possible_movesets = prev_button.move_to(&next_button);
-			let moveset_cost = possible_movesets.into_iter().map(|moveset| {
-				let (cost, _) = moveset.into_iter().map(DirectionalKeypad::from).chain(once(DirectionalKeypad::Activate))
-					.fold((0, DirectionalKeypad::default()), |(sum, prev_btn), next_btn| {
-						(sum + get_cost(&prev_btn, &next_btn, robot_chain_len - 1), next_btn)
-					});
-				cost
-			});
-			let min_cost = moveset_cost.min().unwrap();
+		code_cost_cached(&input_code, robot_chain_len - 1, &mut cost_cache) * numeric_code
+	});
+
+	Ok(complexities.sum())
+}
+
+// number of presses the human needs to make to type the code, without multiplying by its numeric value
+pub fn code_cost(code: &[NumericKeypad], robot_chain_len: u64) -> Result<u64> {
+	let Some(depth) = robot_chain_len.checked_sub(1) else {
+		bail!("need at least one robot to press the numeric keypad");
+	};
+	Ok(code_cost_cached(code, depth, &mut CostCache::new()))
+}
+
+fn code_cost_cached(code: &[NumericKeypad], depth: u64, cache: &mut CostCache) -> u64 {
+	let (min_cost, _) = code.iter().fold((0, NumericKeypad::default()), |(cost, prev_button), &next_button| {
+		let (_, min_cost) = cheapest_moveset(prev_button.move_to(&next_button), depth, cache);
+
+		(cost + min_cost, next_button)
+	});
+	min_cost
+}
+
+// rebuilds one of the cheapest sequences of presses the human has to make to type the code
+pub fn shortest_sequence(code: &[NumericKeypad], robot_chain_len: u64) -> Result<Vec<DirectionalKeypad>> {
+	fn expand(prev_button: DirectionalKeypad, next_button: DirectionalKeypad, depth: u64, cache: &mut CostCache, presses: &mut Vec<DirectionalKeypad>) {
+		if depth == 0 {
+			presses.push(next_button);
+			return;
+		}
+		let (moveset, _) = cheapest_moveset(prev_button.move_to(&next_button), depth - 1, cache);
+		expand_moveset(&moveset, depth - 1, cache, presses);
+	}
 
-			(cost + min_cost, next_button)
+	fn expand_moveset(moveset: &[Direction], depth: u64, cache: &mut CostCache, presses: &mut Vec<DirectionalKeypad>) {
+		moveset_buttons(moveset).fold(DirectionalKeypad::default(), |prev_btn, next_btn| {
+			expand(prev_btn, next_btn, depth, cache, presses);
+			next_btn
 		});
+	}
 
-		input_min_cost * numeric_code
+	let Some(depth) = robot_chain_len.checked_sub(1) else {
+		bail!("need at least one robot to press the numeric keypad");
+	};
+	let mut cost_cache = CostCache::new();
+	let mut presses = Vec::new();
+	code.iter().fold(NumericKeypad::default(), |prev_button, &next_button| {
+		let (moveset, _) = cheapest_moveset(prev_button.move_to(&next_button), depth, &mut cost_cache);
+		expand_moveset(&moveset, depth, &mut cost_cache, &mut presses);
+		next_button
 	});
 
-	Ok(complexities.sum())
+	Ok(presses)
 }
 
 pub fn part2(input: &str) -> Result<u64> {
@@ -339,4 +373,124 @@ mod tests {
 		assert_eq!(126384, part1(TEST)?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_chain_length_scaling() -> Result<()> {
+		// every extra robot in the chain has to spell out the previous robot's presses,
+		// so the complexity of a fixed code has to keep growing with the chain length
+		let complexities = (1..=6).map(|chain_len| solve("029A", chain_len)).collect::<Result<Vec<_>>>()?;
+		assert_eq!(68 * 29, complexities[2]);
+		assert!(complexities.windows(2).all(|pair| pair[0] < pair[1]));
+		Ok(())
+	}
+
+	#[test]
+	fn test_code_cost() -> Result<()> {
+		let code = parse("029A").remove(0);
+		assert_eq!(68, code_cost(&code, 3)?);
+		assert_eq!(shortest_sequence(&code, 3)?.len() as u64, code_cost(&code, 3)?);
+		// a single robot only has to be told how to reach each button and press it
+		assert_eq!(12, code_cost(&code, 1)?);
+		// the numeric keypad can't be reached without a robot, same as solve
+		assert!(code_cost(&code, 0).is_err());
+		assert!(shortest_sequence(&code, 0).is_err());
+		assert!(solve("029A", 0).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_shortest_sequence_replays() -> Result<()> {
+		// plays a sequence of presses on a directional keypad, returning what the robot it controls presses
+		fn replay_directional(presses: &[DirectionalKeypad]) -> Vec<DirectionalKeypad> {
+			const BUTTONS: [DirectionalKeypad; 5] = [
+				DirectionalKeypad::Up, DirectionalKeypad::Left, DirectionalKeypad::Down,
+				DirectionalKeypad::Right, DirectionalKeypad::Activate
+			];
+			let mut pos = DirectionalKeypad::default().get_pos();
+			presses.iter().filter_map(|press| {
+				let movement = match press {
+					DirectionalKeypad::Up => Direction::North,
+					DirectionalKeypad::Left => Direction::West,
+					DirectionalKeypad::Down => Direction::South,
+					DirectionalKeypad::Right => Direction::East,
+					DirectionalKeypad::Activate => {
+						return BUTTONS.into_iter().find(|button| button.get_pos() == pos);
+					}
+				};
+				pos = movement.offset_from(pos).unwrap();
+				assert!(BUTTONS.into_iter().any(|button| button.get_pos() == pos), "robot arm over the gap");
+				None
+			}).collect()
+		}
+
+		fn replay_numeric(presses: &[DirectionalKeypad]) -> Vec<NumericKeypad> {
+			const BUTTONS: [NumericKeypad; 11] = [
+				NumericKeypad::Zero, NumericKeypad::One, NumericKeypad::Two, NumericKeypad::Three,
+				NumericKeypad::Four, NumericKeypad::Five, NumericKeypad::Six, NumericKeypad::Seven,
+				NumericKeypad::Eight, NumericKeypad::Nine, NumericKeypad::Activate
+			];
+			let mut pos = NumericKeypad::default().get_pos();
+			presses.iter().filter_map(|press| {
+				let movement = match press {
+					DirectionalKeypad::Up => Direction::North,
+					DirectionalKeypad::Left => Direction::West,
+					DirectionalKeypad::Down => Direction::South,
+					DirectionalKeypad::Right => Direction::East,
+					DirectionalKeypad::Activate => {
+						return BUTTONS.into_iter().find(|button| button.get_pos() == pos);
+					}
+				};
+				pos = movement.offset_from(pos).unwrap();
+				assert!(BUTTONS.into_iter().any(|button| button.get_pos() == pos), "robot arm over the gap");
+				None
+			}).collect()
+		}
+
+		for line in TEST.lines() {
+			let code = parse(line).remove(0);
+			let numeric_code: u64 = line.trim_end_matches('A').parse()?;
+			for chain_len in 1..=4 {
+				let sequence = shortest_sequence(&code, chain_len)?;
+				assert_eq!(solve(line, chain_len)?, sequence.len() as u64 * numeric_code);
+
+				let robot_presses = (1..chain_len).fold(sequence, |presses, _| replay_directional(&presses));
+				assert_eq!(code, replay_numeric(&robot_presses));
+			}
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_move_to_avoids_gap() {
+		use Direction::*;
+
+		assert_eq!(vec![vec![West]], NumericKeypad::Activate.move_to(&NumericKeypad::Zero));
+		let mut moves = NumericKeypad::Activate.move_to(&NumericKeypad::One);
+		moves.sort_by_key(|moveset| moveset.iter().map(|&dir| DirectionalKeypad::from(dir).get_pos()).collect_vec());
+		assert_eq!(vec![vec![North, West, West], vec![West, North, West]], moves);
+		assert_eq!(vec![vec![East, North]], DirectionalKeypad::Left.move_to(&DirectionalKeypad::Up));
+
+		// no move between any two numeric buttons ever passes over the empty bottom left corner
+		let numeric_gap = (3, 0);
+		let buttons = [
+			NumericKeypad::Zero, NumericKeypad::One, NumericKeypad::Two, NumericKeypad::Three,
+			NumericKeypad::Four, NumericKeypad::Five, NumericKeypad::Six, NumericKeypad::Seven,
+			NumericKeypad::Eight, NumericKeypad::Nine, NumericKeypad::Activate
+		];
+		for (from, to) in buttons.into_iter().cartesian_product(buttons) {
+			let moves = from.move_to(&to);
+			assert!(!moves.is_empty());
+			assert!(moves.into_iter().all(|moveset| {
+				moveset.into_iter().scan(from.get_pos(), |pos, dir| {
+					*pos = dir.offset_from(*pos).unwrap();
+					Some(*pos)
+				}).all(|pos| pos != numeric_gap)
+			}));
+		}
+	}
+
+	#[test]
+	fn test_empty_chain() {
+		assert!(solve("029A", 0).is_err());
+	}
 }
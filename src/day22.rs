@@ -6,14 +6,17 @@ use nom::character::complete::{digit1, line_ending};
 use nom::combinator::{all_consuming, map_res};
 use nom::{Finish, IResult};
 use nom::multi::separated_list1;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
 fn parse_u64(input: &str) -> IResult<&str, u64> {
 	map_res(digit1, |num: &str| num.parse())(input)
 }
 
-fn parse(input: &str) -> Vec<u64> {
-	let (_, result) = all_consuming(separated_list1(line_ending, parse_u64))(input).finish().unwrap();
-	result
+pub fn parse_input(input: &str) -> Result<Vec<u64>> {
+	let (_, result) = all_consuming(separated_list1(line_ending, parse_u64))(input).finish()
+		.map_err(|err| anyhow!("could not parse input at {:?}: {:?}", err.input.lines().next().unwrap_or_default(), err.code))?;
+	Ok(result)
 }
 
 fn mix(a: u64, b: u64) -> u64 {
@@ -24,7 +27,9 @@ fn prune(a: u64) -> u64 {
 	a % 16777216
 }
 
+// secrets are always pruned to 24 bits, so the largest intermediate value here is under 2^35
 fn generate_next_secret(secret: u64) -> u64 {
+	debug_assert!(secret < 16777216, "secret {} was not pruned", secret);
 	let phase1 = prune(mix(secret, secret * 64));
 	let phase2 = prune(mix(phase1, phase1 / 32));
 	let phase3 = prune(mix(phase2, phase2 * 2048));
@@ -32,47 +37,110 @@ fn generate_next_secret(secret: u64) -> u64 {
 	phase3
 }
 
+// every secret a buyer generates, starting with their seed; the seed is pruned first so that
+// it keeps the same 24 bit invariant as every secret generated after it
+fn buyer_secrets(seed: u64) -> impl Iterator<Item = u64> {
+	successors(Some(prune(seed)), |&secret| Some(generate_next_secret(secret)))
+}
+
+pub struct Day22;
+
+// buyers are independent, so with the parallel feature they're spread across threads; on a ~2000 buyer
+// input this should scale with the number of cores, part2 a little less due to merging the per-thread maps
 pub fn part1(input: &str) -> Result<u64> {
-	let seeds = parse(input);
+	Ok(part1_parsed(&parse_input(input)?))
+}
 
-	let new_secrets = seeds.into_iter().map(|seed| {
-		successors(Some(seed), |&secret| Some(generate_next_secret(secret)))
-	}).map(|mut seed_sequence| seed_sequence.nth(2000).unwrap());
+pub fn part1_parsed(seeds: &[u64]) -> u64 {
+	let nth_secret = |&seed| {
+		buyer_secrets(seed).nth(2000).unwrap()
+	};
+	#[cfg(feature = "parallel")]
+	let new_secrets = seeds.par_iter().map(nth_secret);
+	#[cfg(not(feature = "parallel"))]
+	let new_secrets = seeds.iter().map(nth_secret);
 
-	Ok(new_secrets.sum())
+	new_secrets.sum()
 }
 
-pub fn part2(input: &str) -> Result<u64> {
-	let seeds = parse(input);
+pub type Sequence = (i8, i8, i8, i8);
+
+// the first count prices a buyer offers, starting with the price of their initial secret
+pub fn buyer_prices(seed: u64, count: usize) -> Vec<i8> {
+	buyer_secrets(seed)
+		.take(count)
+		.map(|secret| (secret % 10) as i8)
+		.collect()
+}
 
-	let all_buyer_secrets = seeds.into_iter().map(|secret|
-		successors(Some(secret), |&secret| Some(generate_next_secret(secret))).take(2000)
-	);
-	let sequence_profit_map = all_buyer_secrets.fold(AHashMap::new(), |sequence_profit_map, secrets| {
-		let prices = secrets.map(|secret| (secret % 10) as i8);
-		let deltas = prices.clone().tuple_windows().map(|(first, second)| second - first);
+// the first count price changes a buyer goes through, one between each pair of consecutive prices
+pub fn buyer_deltas(seed: u64, count: usize) -> Vec<i8> {
+	buyer_prices(seed, count + 1).into_iter().tuple_windows().map(|(first, second)| second - first).collect()
+}
 
-		// sequences are defined as an ordered collection of four price changes
-		let sequences = deltas.tuple_windows::<(_, _, _, _)>();
+// price a single buyer sells at the first time each sequence of price changes shows up
+fn buyer_sequence_prices(seed: u64) -> AHashMap<Sequence, i8> {
+	let prices = buyer_prices(seed, 2000);
+	let deltas = prices.iter().tuple_windows().map(|(first, second)| second - first);
 
-		// we will never sell on the first four prices provided by a buyer as there will not yet be
-		// enough information to specify a sequence of four price changes on which to sell
-		let sequence_price_mappings = sequences.zip(prices.skip(4));
+	// sequences are defined as an ordered collection of four price changes
+	let sequences = deltas.tuple_windows::<(_, _, _, _)>();
 
-		let sequence_price_map = sequence_price_mappings.fold(AHashMap::new(), |mut sequence_price_map, (sequence, price)| {
-			sequence_price_map.entry(sequence).or_insert(price);
-			sequence_price_map
-		});
+	// we will never sell on the first four prices provided by a buyer as there will not yet be
+	// enough information to specify a sequence of four price changes on which to sell
+	let sequence_price_mappings = sequences.zip(prices.iter().copied().skip(4));
 
-		sequence_price_map.into_iter().fold(sequence_profit_map, |mut profit_map, (sequence, price)| {
-			profit_map.entry(sequence)
-				.and_modify(|profit| { *profit += price as u64; })
-				.or_insert(price as u64);
-			profit_map
-		})
+	sequence_price_mappings.fold(AHashMap::new(), |mut sequence_price_map, (sequence, price)| {
+		sequence_price_map.entry(sequence).or_insert(price);
+		sequence_price_map
+	})
+}
+
+fn add_buyer(mut profit_map: AHashMap<Sequence, u64>, sequence_price_map: AHashMap<Sequence, i8>) -> AHashMap<Sequence, u64> {
+	sequence_price_map.into_iter().for_each(|(sequence, price)| {
+		*profit_map.entry(sequence).or_insert(0) += price as u64;
 	});
+	profit_map
+}
+
+fn sequence_profit_map(seeds: &[u64]) -> AHashMap<Sequence, u64> {
+	#[cfg(feature = "parallel")]
+	let sequence_profit_map = seeds.par_iter().copied().map(buyer_sequence_prices)
+		.fold(AHashMap::new, add_buyer)
+		.reduce(AHashMap::new, |profit_map, other_profit_map| {
+			other_profit_map.into_iter().fold(profit_map, |mut profit_map, (sequence, profit)| {
+				*profit_map.entry(sequence).or_insert(0) += profit;
+				profit_map
+			})
+		});
+	#[cfg(not(feature = "parallel"))]
+	let sequence_profit_map = seeds.iter().copied().map(buyer_sequence_prices)
+		.fold(AHashMap::new(), add_buyer);
+
+	sequence_profit_map
+}
+
+pub fn part2(input: &str) -> Result<u64> {
+	part2_parsed(&parse_input(input)?)
+}
+
+pub fn part2_parsed(seeds: &[u64]) -> Result<u64> {
+	let (_, profit) = best_sequence_parsed(seeds)?;
+
+	Ok(profit)
+}
 
-	Ok(sequence_profit_map.into_values().max().unwrap())
+pub fn best_sequence(input: &str) -> Result<(Sequence, u64)> {
+	best_sequence_parsed(&parse_input(input)?)
+}
+
+fn best_sequence_parsed(seeds: &[u64]) -> Result<(Sequence, u64)> {
+	// prefer the lexicographically smallest sequence when several share the best profit
+	sequence_profit_map(seeds).into_iter()
+		.max_by(|(sequence, profit), (other_sequence, other_profit)| {
+			profit.cmp(other_profit).then_with(|| other_sequence.cmp(sequence))
+		})
+		.ok_or_else(|| anyhow!("no buyer produced a full sequence of price changes"))
 }
 
 #[cfg(test)]
@@ -96,4 +164,50 @@ mod tests {
 2024")?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_parse_input() -> Result<()> {
+		let seeds = parse_input("1
+2
+3
+2024")?;
+		assert_eq!(vec![1, 2, 3, 2024], seeds);
+		assert_eq!(part1("1\n2\n3\n2024")?, part1_parsed(&seeds));
+		assert_eq!(23, part2_parsed(&seeds)?);
+		assert!(parse_input("1\n2\nthree").is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_buyer_prices() {
+		assert_eq!(vec![3, 0, 6, 5, 4, 4, 6, 4, 4, 2], buyer_prices(123, 10));
+		assert_eq!(vec![-3, 6, -1, -1, 0, 2, -2, 0, -2], buyer_deltas(123, 9));
+	}
+
+	#[test]
+	fn test_large_seed() -> Result<()> {
+		// 16777217 prunes down to 1
+		assert_eq!(buyer_secrets(1).take(10).collect::<Vec<_>>(), buyer_secrets(16777217).take(10).collect::<Vec<_>>());
+		assert_eq!(buyer_prices(1, 2001), buyer_prices(16777217, 2001));
+		assert_eq!(part1("1")?, part1("16777217")?);
+		// would overflow the first multiplication if it wasn't pruned
+		assert_eq!(part1(&(u64::MAX % 16777216).to_string())?, part1(&u64::MAX.to_string())?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_best_sequence() -> Result<()> {
+		const INPUT: &str = "1
+2
+3
+2024";
+		let (sequence, profit) = best_sequence(INPUT)?;
+		assert_eq!(((-2, 1, -1, 3), 23), (sequence, profit));
+
+		let replayed: u64 = parse_input(INPUT)?.into_iter()
+			.filter_map(|seed| buyer_sequence_prices(seed).get(&sequence).map(|&price| price as u64))
+			.sum();
+		assert_eq!(profit, replayed);
+		Ok(())
+	}
 }
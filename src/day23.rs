@@ -16,13 +16,17 @@ fn parse_line(input: &str) -> IResult<&str, (&str, &str)> {
 	)(input)
 }
 
-fn parse(input: &str) -> Vec<(&str, &str)> {
-	let (_, result) = all_consuming(separated_list1(line_ending, parse_line))(input).finish().unwrap();
-	result
+fn parse(input: &str) -> Result<Vec<(&str, &str)>> {
+	let (_, result) = all_consuming(separated_list1(line_ending, parse_line))(input).finish()
+		.map_err(|err| anyhow!("could not parse input at {:?}: {:?}", err.input.lines().next().unwrap_or_default(), err.code))?;
+	Ok(result)
 }
 
-pub fn part1(input: &str) -> Result<usize> {
-	let connection_pairs = parse(input);
+// every computer mapped to the computers it is directly connected to
+pub type ConnectionMap<'a> = AHashMap<&'a str, BTreeSet<&'a str>>;
+
+pub fn parse_input(input: &str) -> Result<ConnectionMap<'_>> {
+	let connection_pairs = parse(input)?;
 
 	let connection_map = connection_pairs.into_iter().fold(AHashMap::new(), |mut connection_map, (node_one, node_two)| {
 		connection_map.entry(node_one)
@@ -34,7 +38,32 @@ pub fn part1(input: &str) -> Result<usize> {
 
 		connection_map
 	});
+	Ok(connection_map)
+}
+
+// how many computers each computer is directly connected to
+pub fn node_degrees(input: &str) -> Result<AHashMap<String, usize>> {
+	let connection_map = parse_input(input)?;
+
+	Ok(connection_map.into_iter().map(|(node, neighbors)| (node.to_string(), neighbors.len())).collect())
+}
 
+pub struct Day23;
+
+pub fn part1(input: &str) -> Result<usize> {
+	Ok(part1_parsed(&parse_input(input)?))
+}
+
+pub fn part1_parsed(connection_map: &ConnectionMap) -> usize {
+	triangles_with_t_parsed(connection_map).len()
+}
+
+// every set of three interconnected computers where at least one name starts with t, each sorted by name
+pub fn triangles_with_t(input: &str) -> Result<Vec<[String; 3]>> {
+	Ok(triangles_with_t_parsed(&parse_input(input)?))
+}
+
+fn triangles_with_t_parsed(connection_map: &ConnectionMap) -> Vec<[String; 3]> {
 	let nodes_to_check = connection_map.iter().filter(|(node_name, _)| node_name.starts_with('t'));
 	let three_member_connections = nodes_to_check.into_iter().fold(AHashSet::new(), |three_nodes_set, (curr_node, curr_neighbors)| {
 		// for any given node, iterate through its list of neighbors
@@ -50,74 +79,64 @@ pub fn part1(input: &str) -> Result<usize> {
 		})
 	});
 
-	Ok(three_member_connections.len())
+	three_member_connections.into_iter()
+		.map(|triangle| {
+			let (first, second, third) = triangle.into_iter().map(|node| node.to_string()).collect_tuple().unwrap();
+			[first, second, third]
+		})
+		.sorted_unstable()
+		.collect()
 }
 
 pub fn part2(input: &str) -> Result<String> {
-	let connection_pairs = parse(input);
-
-	let connection_map = connection_pairs.into_iter().fold(AHashMap::new(), |mut connection_map, (node_one, node_two)| {
-		connection_map.entry(node_one)
-			.and_modify(|node_one_entry: &mut BTreeSet<_>| { node_one_entry.insert(node_two); })
-			.or_insert(BTreeSet::from([node_two]));
-		connection_map.entry(node_two)
-			.and_modify(|node_two_entry| { node_two_entry.insert(node_one); })
-			.or_insert(BTreeSet::from([node_one]));
-
-		connection_map
-	});
+	Ok(part2_parsed(&parse_input(input)?))
+}
 
-	let max_clique = |set: &BTreeSet<_>| {
-		fn bron_kerbosch(r: BTreeSet<&str>, p: BTreeSet<&str>, x: BTreeSet<&str>, connection_map: &AHashMap<&str, BTreeSet<&str>>) -> BTreeSet<BTreeSet<String>> {
-			if p.is_empty() && x.is_empty() {
-				BTreeSet::from([BTreeSet::from_iter(r.into_iter().map(|str_slice| str_slice.to_string()))])
-			} else {
-				// let (pivot, pivot_neighbors) = p.union(&x)
-				// 	.map(|key| (key, connection_map.get(key).unwrap()))
-				// 	.min_by_key(|(_, nbr_set)| nbr_set.len()).unwrap();
-				// let rec_call = p.clone().into_iter().filter(|&key| !pivot_neighbors.contains(key))
-				// 	.try_fold((p, x), |(mut p, mut x), key| {
-				// 		let new_r = BTreeSet::from_iter(r.union(&BTreeSet::from([key])).cloned());
-				// 		let new_p = BTreeSet::from_iter(p.intersection(connection_map.get(key).unwrap()).cloned());
-				// 		let new_x = BTreeSet::from_iter(x.intersection(connection_map.get(key).unwrap()).cloned());
-				// 		let result = bron_kerbosch(new_r, new_p, new_x, connection_map);
-				// 		if let Some(result) = result {
-				// 			ControlFlow::Break(result)
-				// 		} else {
-				// 			p.remove(key);
-				// 			x.insert(key);
-				// 			ControlFlow::Continue((p, x))
-				// 		}
-				// });
-
-				let (_, _, result) = p.clone().into_iter()
-					.fold((p, x, BTreeSet::default()), |(mut p, mut x, mut results), key| {
-						let new_r = BTreeSet::from_iter(r.union(&BTreeSet::from([key])).cloned());
-						let new_p = BTreeSet::from_iter(p.intersection(connection_map.get(key).unwrap()).cloned());
-						let new_x = BTreeSet::from_iter(x.intersection(connection_map.get(key).unwrap()).cloned());
-						bron_kerbosch(new_r, new_p, new_x, connection_map).into_iter().for_each(|result| {
-							results.insert(result);
-						});
-
-						p.remove(key);
-						x.insert(key);
-
-						(p, x, results)
-					});
-				result
+pub fn part2_parsed(connection_map: &ConnectionMap) -> String {
+	// bron-kerbosch with pivoting, only remembering the largest clique seen so far
+	fn bron_kerbosch<'a>(
+		r: &mut Vec<&'a str>,
+		mut p: BTreeSet<&'a str>,
+		mut x: BTreeSet<&'a str>,
+		connection_map: &ConnectionMap<'a>,
+		best: &mut Vec<&'a str>,
+	) {
+		if p.is_empty() && x.is_empty() {
+			if r.len() > best.len() {
+				best.clone_from(r);
 			}
+			return;
+		}
+		// can't beat the current best even by taking every remaining candidate
+		if r.len() + p.len() <= best.len() {
+			return;
 		}
 
-		bron_kerbosch(BTreeSet::default(), set.clone(), BTreeSet::default(), &connection_map)
-	};
+		// any maximal clique must contain the pivot or one of its non-neighbors,
+		// so choosing the pivot covering the most candidates leaves the fewest branches
+		let pivot_neighbors = p.union(&x)
+			.map(|key| connection_map.get(key).unwrap())
+			.max_by_key(|nbr_set| p.intersection(nbr_set).count()).unwrap();
+		let branch_keys = p.difference(pivot_neighbors).copied().collect_vec();
+
+		branch_keys.into_iter().for_each(|key| {
+			let key_neighbors = connection_map.get(key).unwrap();
+			let new_p = p.intersection(key_neighbors).copied().collect();
+			let new_x = x.intersection(key_neighbors).copied().collect();
+			r.push(key);
+			bron_kerbosch(r, new_p, new_x, connection_map, best);
+			r.pop();
+
+			p.remove(key);
+			x.insert(key);
+		});
+	}
 
 	let all_nodes = BTreeSet::from_iter(connection_map.keys().cloned());
-	let max_clique_set = max_clique(&all_nodes);
-	// println!("max_clique_set: {max_clique_set:?}");
-	let maximum_clique = max_clique_set.into_iter().max_by_key(|clique| clique.len()).unwrap();
-	// println!("maximum_clique: {maximum_clique:?}");
+	let mut maximum_clique = Vec::new();
+	bron_kerbosch(&mut Vec::new(), all_nodes, BTreeSet::default(), connection_map, &mut maximum_clique);
 
-	Ok(maximum_clique.into_iter().join(","))
+	maximum_clique.into_iter().sorted().join(",")
 }
 
 #[cfg(test)]
@@ -163,9 +182,40 @@ td-yn";
 		Ok(())
 	}
 
+	#[test]
+	fn test_triangles_with_t() -> Result<()> {
+		let triangles = triangles_with_t(TEST)?;
+		assert_eq!(7, triangles.len());
+		assert!(triangles.iter().all(|triangle| triangle.iter().any(|node| node.starts_with('t'))));
+		assert_eq!(["co", "de", "ta"], triangles[0]);
+		Ok(())
+	}
+
+	#[test]
+	fn test_node_degrees() -> Result<()> {
+		let degrees = node_degrees(TEST)?;
+		assert_eq!(16, degrees.len());
+		// ka, ta, de and tc
+		assert_eq!(Some(&4), degrees.get("co"));
+		// every connection adds to the degree of both of its computers
+		assert_eq!(TEST.lines().count() * 2, degrees.values().sum::<usize>());
+		Ok(())
+	}
+
 	#[test]
 	fn test_part_two() -> Result<()> {
 		assert_eq!("co,de,ka,ta", part2(TEST)?);
 		Ok(())
 	}
+
+	#[test]
+	fn test_parse_input() -> Result<()> {
+		let connection_map = parse_input(TEST)?;
+		assert_eq!(16, connection_map.len());
+		assert_eq!(BTreeSet::from(["cg", "co", "ka", "ta"]), connection_map["de"]);
+		assert_eq!(part1(TEST)?, part1_parsed(&connection_map));
+		assert_eq!(part2(TEST)?, part2_parsed(&connection_map));
+		assert!(parse_input("kh-tc\nqp-").is_err());
+		Ok(())
+	}
 }
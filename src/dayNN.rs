@@ -1,6 +1,8 @@
 use anyhow::*;
 use itertools::Itertools;
 
+pub struct DayNN;
+
 pub fn part1(input: &str) -> Result<u64> {
 	// TODO: Solve Part 1 of the puzzle
 	let _ = input;
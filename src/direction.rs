@@ -0,0 +1,90 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+	North,
+	East,
+	South,
+	West,
+}
+impl Direction {
+	pub const fn get_offset(&self) -> (isize, isize) {
+		match self {
+			Direction::North => (-1, 0),
+			Direction::East => (0, 1),
+			Direction::South => (1, 0),
+			Direction::West => (0, -1),
+		}
+	}
+
+	pub fn offset_from(&self, (row, col): (impl TryInto<isize>, impl TryInto<isize>)) -> Option<(isize, isize)> {
+		let row_isize = row.try_into().ok()?;
+		let col_isize = col.try_into().ok()?;
+		let (row_offset, col_offset) = self.get_offset();
+		Some((row_isize + row_offset, col_isize + col_offset))
+	}
+
+	pub const fn values() -> [Direction; 4] {
+		[
+			Direction::North,
+			Direction::East,
+			Direction::South,
+			Direction::West
+		]
+	}
+
+	pub const fn opposite(&self) -> Direction {
+		match self {
+			Direction::North => Direction::South,
+			Direction::East => Direction::West,
+			Direction::South => Direction::North,
+			Direction::West => Direction::East,
+		}
+	}
+
+	pub const fn perpendicular(&self) -> [Direction; 2] {
+		match self {
+			Direction::North | Direction::South => [
+				Direction::East, Direction::West
+			],
+			Direction::East | Direction::West => [
+				Direction::North, Direction::South
+			]
+		}
+	}
+
+	// clockwise
+	pub const fn turn_right(&self) -> Self {
+		match self {
+			Direction::North => Direction::East,
+			Direction::East => Direction::South,
+			Direction::South => Direction::West,
+			Direction::West => Direction::North,
+		}
+	}
+
+	// counter-clockwise
+	pub const fn turn_left(&self) -> Self {
+		match self {
+			Direction::North => Direction::West,
+			Direction::East => Direction::North,
+			Direction::South => Direction::East,
+			Direction::West => Direction::South,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::direction::*;
+
+	#[test]
+	fn test_turns() {
+		for dir in Direction::values() {
+			assert_eq!(dir, dir.turn_right().turn_right().turn_right().turn_right());
+			assert_eq!(dir, dir.turn_left().turn_left().turn_left().turn_left());
+			assert_eq!(dir, dir.turn_right().turn_left());
+			assert_eq!(dir, dir.turn_left().turn_right());
+			assert_eq!(dir.opposite(), dir.turn_right().turn_right());
+			assert!(dir.perpendicular().contains(&dir.turn_left()));
+		}
+	}
+}
@@ -0,0 +1,54 @@
+use grid::Grid;
+use itertools::Itertools;
+
+// grid's own get accepts anything convertible to usize, which only rejects negative coordinates because
+// they wrap around to huge values, so check for them explicitly instead
+pub trait SignedGridExt<T> {
+	fn get_signed(&self, row: isize, col: isize) -> Option<&T>;
+	fn get_signed_mut(&mut self, row: isize, col: isize) -> Option<&mut T>;
+}
+impl<T> SignedGridExt<T> for Grid<T> {
+	fn get_signed(&self, row: isize, col: isize) -> Option<&T> {
+		let row = usize::try_from(row).ok()?;
+		let col = usize::try_from(col).ok()?;
+		self.get(row, col)
+	}
+
+	fn get_signed_mut(&mut self, row: isize, col: isize) -> Option<&mut T> {
+		let row = usize::try_from(row).ok()?;
+		let col = usize::try_from(col).ok()?;
+		self.get_mut(row, col)
+	}
+}
+
+// one line per row, for dumping a map while debugging
+pub fn render<T>(grid: &Grid<T>, cell_char: impl Fn(&T) -> char) -> String {
+	grid.iter_rows().map(|cells| cells.map(&cell_char).collect::<String>()).join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+	use grid::grid;
+	use crate::grid_ext::*;
+
+	#[test]
+	fn test_get_signed() {
+		let mut grid = grid![[1, 2][3, 4]];
+		assert_eq!(Some(&3), grid.get_signed(1, 0));
+		assert_eq!(None, grid.get_signed(-1, 0));
+		assert_eq!(None, grid.get_signed(0, -1));
+		assert_eq!(None, grid.get_signed(2, 0));
+		assert_eq!(None, grid.get_signed(isize::MIN, isize::MIN));
+
+		*grid.get_signed_mut(0, 1).unwrap() = 5;
+		assert_eq!(Some(&5), grid.get_signed(0, 1));
+		assert!(grid.get_signed_mut(-1, 0).is_none());
+	}
+
+	#[test]
+	fn test_render() {
+		let grid = grid![[1, 2, 3][4, 5, 6]];
+		assert_eq!("123\n456", render(&grid, |&cell| char::from_digit(cell, 10).unwrap()));
+		assert_eq!("#.#\n.#.", render(&grid, |&cell| if cell % 2 == 1 { '#' } else { '.' }));
+	}
+}
@@ -0,0 +1,51 @@
+use anyhow::*;
+use grid::Grid;
+
+pub fn parse_grid<T>(input: &str, cell: impl Fn(char) -> Option<T>) -> Result<Grid<T>> {
+	let mut grid_cols = None;
+	let mut cells = Vec::new();
+
+	for (row, line) in input.lines().enumerate() {
+		let row_len = line.chars().count();
+		match grid_cols {
+			None => grid_cols = Some(row_len),
+			Some(cols) if cols != row_len => bail!("row {} has {} cells, expected {}", row, row_len, cols),
+			_ => {}
+		}
+
+		for (col, chr) in line.chars().enumerate() {
+			let parsed = cell(chr).ok_or_else(|| anyhow!("unexpected character {:?} at row {}, col {}", chr, row, col))?;
+			cells.push(parsed);
+		}
+	}
+
+	match grid_cols {
+		Some(cols) if cols > 0 => Ok(Grid::from_vec(cells, cols)),
+		_ => bail!("grid input is empty"),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::grid_parse::*;
+
+	#[test]
+	fn test_parse_grid() -> Result<()> {
+		let grid = parse_grid("12\n34\n", |chr| chr.to_digit(10))?;
+		assert_eq!((2, 2), grid.size());
+		assert_eq!(Some(&3), grid.get(1, 0));
+		Ok(())
+	}
+
+	#[test]
+	fn test_ragged_rows() -> Result<()> {
+		assert!(parse_grid("123\n45\n678", |chr| chr.to_digit(10)).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_unexpected_character() -> Result<()> {
+		assert!(parse_grid("12\n3x", |chr| chr.to_digit(10)).is_err());
+		Ok(())
+	}
+}
@@ -1,4 +1,5 @@
-use std::fs;
+use std::fs::File;
+use std::io::{self, Read};
 use anyhow::*;
 
 pub mod day01;
@@ -25,8 +26,111 @@ pub mod day21;
 pub mod day22;
 pub mod day23;
 
+pub mod direction;
+pub mod grid_ext;
+pub mod grid_parse;
+pub mod memo;
+pub mod pathfind;
+
 pub fn start_day(day: &str) -> Result<String> {
+	start_day_from(day, File::open(format!("input/{}.txt", day))?)
+}
+
+pub fn read_stdin(day: &str) -> Result<String> {
+	start_day_from(day, io::stdin())
+}
+
+pub fn start_day_from<R: Read>(day: &str, mut reader: R) -> Result<String> {
 	println!("Advent of Code 2024 - Day {:0>2}", day);
 
-	Ok(fs::read_to_string(format!("input/{}.txt", day))?)
+	let mut input = String::new();
+	reader.read_to_string(&mut input)?;
+	Ok(input)
+}
+
+// every day behind a common interface, with answers stringified regardless of their native type
+pub trait Solution {
+	fn part1(&self, input: &str) -> Result<String>;
+	fn part2(&self, input: &str) -> Result<String>;
+}
+
+macro_rules! solutions {
+	($($key:literal => $day:ident::$name:ident),* $(,)?) => {
+		$(
+			impl Solution for $day::$name {
+				fn part1(&self, input: &str) -> Result<String> {
+					$day::part1(input).map(|result| result.to_string())
+				}
+
+				fn part2(&self, input: &str) -> Result<String> {
+					$day::part2(input).map(|result| result.to_string())
+				}
+			}
+		)*
+
+		pub fn solutions() -> Vec<(&'static str, Box<dyn Solution>)> {
+			vec![$(($key, Box::new($day::$name))),*]
+		}
+	};
+}
+
+solutions!(
+	"01" => day01::Day01, "02" => day02::Day02, "03" => day03::Day03, "04" => day04::Day04, "05" => day05::Day05, "06" => day06::Day06,
+	"07" => day07::Day07, "08" => day08::Day08, "09" => day09::Day09, "10" => day10::Day10, "11" => day11::Day11, "12" => day12::Day12,
+	"13" => day13::Day13, "14" => day14::Day14, "15" => day15::Day15, "16" => day16::Day16, "17" => day17::Day17, "18" => day18::Day18,
+	"19" => day19::Day19, "20" => day20::Day20, "21" => day21::Day21, "22" => day22::Day22, "23" => day23::Day23,
+);
+
+pub fn run_day(day: &str, part: u8, input: &str) -> Result<String> {
+	let Some((_, solution)) = day.parse::<u8>().ok()
+		.map(|day_num| format!("{:0>2}", day_num))
+		.and_then(|day_key| solutions().into_iter().find(|(key, _)| *key == day_key)) else {
+		bail!("no solution for day {} part {}", day, part);
+	};
+
+	match part {
+		1 => solution.part1(input),
+		2 => solution.part2(input),
+		_ => bail!("no solution for day {} part {}", day, part),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::*;
+
+	#[test]
+	fn test_start_day_from() -> Result<()> {
+		const INPUT: &str = "3   4\n4   3\n";
+		assert_eq!(INPUT, start_day_from("01", std::io::Cursor::new(INPUT.as_bytes()))?);
+		assert!(start_day_from("01", std::io::Cursor::new([0xff, 0xfe])).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_solutions() -> Result<()> {
+		let solutions = solutions();
+		assert_eq!(23, solutions.len());
+		assert!(solutions.iter().map(|(day, _)| *day).eq((1..=23).map(|day| format!("{:0>2}", day))));
+
+		let (_, day01) = solutions.iter().find(|(day, _)| *day == "01").unwrap();
+		assert_eq!("11", day01.part1("3   4\n4   3\n2   5\n1   3\n3   9\n3   3")?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_run_day() -> Result<()> {
+		const TEST: &str = "3   4
+4   3
+2   5
+1   3
+3   9
+3   3";
+		assert_eq!("11", run_day("01", 1, TEST)?);
+		assert_eq!("31", run_day("1", 2, TEST)?);
+		assert!(run_day("01", 3, TEST).is_err());
+		assert!(run_day("26", 1, TEST).is_err());
+		assert!(run_day("one", 1, TEST).is_err());
+		Ok(())
+	}
 }
@@ -0,0 +1,49 @@
+use std::hash::Hash;
+use ahash::AHashMap;
+
+// memoizes a recursive function, the recurrence gets the memoizer back so it can recurse through it
+//
+// the recurrence is a plain fn, so anything it needs besides the key has to be part of the key
+pub struct Memoizer<K, V> {
+	cache: AHashMap<K, V>,
+	recurrence: fn(&mut Memoizer<K, V>, K) -> V,
+}
+impl<K: Clone + Eq + Hash, V: Clone> Memoizer<K, V> {
+	pub fn new(recurrence: fn(&mut Memoizer<K, V>, K) -> V) -> Self {
+		Self { cache: AHashMap::new(), recurrence }
+	}
+
+	pub fn get(&mut self, key: K) -> V {
+		if let Some(value) = self.cache.get(&key) {
+			return value.clone();
+		}
+
+		let value = (self.recurrence)(self, key.clone());
+		self.cache.insert(key, value.clone());
+		value
+	}
+
+	pub fn len(&self) -> usize {
+		self.cache.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.cache.is_empty()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::memo::*;
+
+	#[test]
+	fn test_fibonacci() {
+		let mut fibonacci = Memoizer::new(|memo, n: u64| {
+			if n < 2 { n } else { memo.get(n - 1) + memo.get(n - 2) }
+		});
+		assert!(fibonacci.is_empty());
+		assert_eq!(12586269025, fibonacci.get(50));
+		// every value from 0 to 50 is computed exactly once
+		assert_eq!(51, fibonacci.len());
+	}
+}
@@ -0,0 +1,119 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::collections::hash_map::Entry;
+use std::hash::Hash;
+use ahash::AHashMap;
+
+// a payload with a score, ordered so that a BinaryHeap pops the lowest score first
+//
+// only the score takes part in comparisons, the payload doesn't need to be ordered at all
+#[derive(Clone, Copy, Debug)]
+pub struct MinScored<S>(pub u64, pub S);
+impl<S> PartialEq for MinScored<S> {
+	fn eq(&self, other: &Self) -> bool {
+		self.0 == other.0
+	}
+}
+impl<S> Eq for MinScored<S> {}
+impl<S> PartialOrd for MinScored<S> {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+impl<S> Ord for MinScored<S> {
+	fn cmp(&self, other: &Self) -> Ordering {
+		// inverted, lower score is better
+		other.0.cmp(&self.0)
+	}
+}
+
+// finds the cheapest path from start to any goal state, returning its cost and every state along it (start and goal included)
+//
+// the heuristic must never overestimate the remaining cost, a heuristic of zero makes this plain dijkstra
+pub fn astar<S, I>(
+	start: S,
+	mut is_goal: impl FnMut(&S) -> bool,
+	mut successors: impl FnMut(&S) -> I,
+	mut heuristic: impl FnMut(&S) -> u64,
+) -> Option<(u64, Vec<S>)>
+where
+	S: Clone + Eq + Hash,
+	I: IntoIterator<Item = (S, u64)>,
+{
+	let mut queue = BinaryHeap::new();
+	let mut weights = AHashMap::new();
+	let mut came_from: AHashMap<S, S> = AHashMap::new();
+	weights.insert(start.clone(), 0);
+	queue.push(MinScored(heuristic(&start), (0, start)));
+
+	while let Some(MinScored(_, (real_cost, curr_state))) = queue.pop() {
+		if is_goal(&curr_state) {
+			let mut path = vec![curr_state];
+			while let Some(prev_state) = came_from.get(path.last().unwrap()) {
+				path.push(prev_state.clone());
+			}
+			path.reverse();
+			return Some((real_cost, path));
+		}
+		// may insert same node multiple times, skip if already seen with lower cost
+		if let Some(&prev_cost) = weights.get(&curr_state) {
+			if real_cost > prev_cost {
+				continue;
+			}
+		}
+		successors(&curr_state).into_iter().for_each(|(next_state, step_cost)| {
+			let next_cost = real_cost + step_cost;
+			match weights.entry(next_state.clone()) {
+				Entry::Vacant(e) => {
+					e.insert(next_cost);
+				},
+				Entry::Occupied(mut e) => {
+					if next_cost >= *e.get() {
+						return;
+					}
+					e.insert(next_cost);
+				}
+			};
+			came_from.insert(next_state.clone(), curr_state.clone());
+			queue.push(MinScored(next_cost + heuristic(&next_state), (next_cost, next_state)));
+		});
+	}
+
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::pathfind::*;
+
+	#[test]
+	fn test_min_scored() {
+		let mut queue = BinaryHeap::from([MinScored(3, 'c'), MinScored(1, 'a'), MinScored(2, 'b')]);
+		assert_eq!(Some('a'), queue.pop().map(|MinScored(_, payload)| payload));
+		assert_eq!(Some('b'), queue.pop().map(|MinScored(_, payload)| payload));
+		assert_eq!(Some('c'), queue.pop().map(|MinScored(_, payload)| payload));
+	}
+
+	#[test]
+	fn test_line() {
+		// walk along a number line from 0 to 5, one step at a time
+		let result = astar(0i64, |&pos| pos == 5, |&pos| [(pos - 1, 1), (pos + 1, 1)], |&pos| pos.abs_diff(5));
+		assert_eq!(Some((5, vec![0, 1, 2, 3, 4, 5])), result);
+	}
+
+	#[test]
+	fn test_cheaper_detour() {
+		// jumping straight to 3 costs more than stepping there
+		let result = astar(0u64, |&pos| pos == 3, |&pos| {
+			let jump = (pos == 0).then_some((3, 10));
+			[(pos + 1, 1)].into_iter().chain(jump)
+		}, |_| 0);
+		assert_eq!(Some((3, vec![0, 1, 2, 3])), result);
+	}
+
+	#[test]
+	fn test_unreachable() {
+		let result = astar(0u64, |&pos| pos == 10, |&pos| (pos < 5).then_some((pos + 1, 1)), |_| 0);
+		assert_eq!(None, result);
+	}
+}
@@ -0,0 +1,24 @@
+use anyhow::*;
+use aoc_24::{run_day, solutions, start_day};
+
+// expected answers for the committed inputs, one entry per day: (day, part1, part2)
+// a day with an input but no entry here fails, so adding an input means adding its answers
+const ANSWERS: &[(&str, &str, &str)] = &[
+];
+
+#[test]
+fn test_all_days() -> Result<()> {
+	for (day, _) in solutions() {
+		let Result::Ok(input) = start_day(day) else {
+			eprintln!("skipping day {}: input/{}.txt not found", day, day);
+			continue;
+		};
+		let Some(&(_, part1, part2)) = ANSWERS.iter().find(|(answer_day, _, _)| *answer_day == day) else {
+			bail!("no expected answers for day {}, add them to ANSWERS", day);
+		};
+
+		assert_eq!(part1, run_day(day, 1, &input)?, "day {} part 1", day);
+		assert_eq!(part2, run_day(day, 2, &input)?, "day {} part 2", day);
+	}
+	Ok(())
+}